@@ -0,0 +1,145 @@
+use std::path::PathBuf;
+
+use crabgrab::prelude::{CapturableContent, CapturableWindow};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::{
+    audio::audio_settings::AudioSettings,
+    capture::display_identity::{find_display, DisplayIdentity},
+};
+
+use super::app_state::{AppState, CapturableSurface};
+
+const SETTINGS_FILE: &str = "settings.json";
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    pub last_selection: LastSelection,
+    pub audio: AudioSettings,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LastSelection {
+    pub surface: Option<RememberedSurface>,
+    pub audio_device: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum RememberedSurface {
+    Window {
+        application: String,
+        title: String,
+        title_pattern: String,
+    },
+    Display(DisplayIdentity),
+}
+
+impl AppSettings {
+    fn path(handle: &AppHandle) -> Result<PathBuf, String> {
+        let config_dir = handle.path().app_config_dir().map_err(|e| e.to_string())?;
+        Ok(config_dir.join(SETTINGS_FILE))
+    }
+
+    /// Reads the settings file, falling back to the defaults if it is missing or invalid
+    pub fn load(handle: &AppHandle) -> AppSettings {
+        Self::path(handle)
+            .ok()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, handle: &AppHandle) -> Result<(), String> {
+        let path = Self::path(handle)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let contents = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, contents).map_err(|e| e.to_string())
+    }
+}
+
+impl RememberedSurface {
+    pub fn from_surface(surface: &CapturableSurface) -> RememberedSurface {
+        match surface {
+            CapturableSurface::CapturableWindow(window) => {
+                let title = window.title();
+                RememberedSurface::Window {
+                    application: window.application().identifier(),
+                    title_pattern: title_pattern(&title),
+                    title,
+                }
+            }
+            CapturableSurface::CapturableDisplay(display) => {
+                RememberedSurface::Display(DisplayIdentity::of(display))
+            }
+        }
+    }
+
+    /// Looks for the remembered surface among the currently capturable content
+    pub fn find(&self, content: &CapturableContent) -> Option<CapturableSurface> {
+        match self {
+            RememberedSurface::Window {
+                application,
+                title,
+                title_pattern,
+            } => {
+                let pattern = regex::Regex::new(title_pattern).ok();
+                let candidates: Vec<CapturableWindow> = content
+                    .windows()
+                    .filter(|window| &window.application().identifier() == application)
+                    .collect();
+
+                let window = candidates
+                    .iter()
+                    .find(|window| &window.title() == title)
+                    .or_else(|| {
+                        candidates.iter().find(|window| {
+                            pattern.as_ref().map_or(false, |pattern| pattern.is_match(&window.title()))
+                        })
+                    })
+                    // the application has a single window, its title is not needed to tell it apart
+                    .or_else(|| if candidates.len() == 1 { candidates.first() } else { None })?;
+                Some(CapturableSurface::CapturableWindow(window.clone()))
+            }
+            RememberedSurface::Display(identity) => find_display(content.displays(), identity)
+                .map(CapturableSurface::CapturableDisplay),
+        }
+    }
+}
+
+/// Persists the surface the user picked so it can be restored on the next launch
+pub async fn remember_surface(handle: &AppHandle, surface: Option<&CapturableSurface>) -> Result<(), String> {
+    let state = handle
+        .try_state::<AppState>()
+        .ok_or("Failed to get AppState")?;
+    let mut settings = state.settings.lock().await;
+    settings.last_selection.surface = surface.map(RememberedSurface::from_surface);
+    settings.save(handle)
+}
+
+pub async fn remember_audio_device(handle: &AppHandle, device_name: Option<String>) -> Result<(), String> {
+    let state = handle
+        .try_state::<AppState>()
+        .ok_or("Failed to get AppState")?;
+    let mut settings = state.settings.lock().await;
+    settings.last_selection.audio_device = device_name;
+    settings.save(handle)
+}
+
+/// Window titles usually end with the application name after a separator
+/// ("notes.txt - Notepad"), only that suffix is kept so the document may change
+fn title_pattern(title: &str) -> String {
+    let suffix = [" - ", " — ", " | "]
+        .iter()
+        .filter_map(|separator| title.rfind(separator).map(|index| &title[index..]))
+        .min_by_key(|suffix| suffix.len());
+
+    match suffix {
+        Some(suffix) => format!("^.*{}$", regex::escape(suffix)),
+        None => format!("^{}$", regex::escape(title)),
+    }
+}
@@ -1,7 +1,19 @@
 use crabgrab::prelude::{CapturableDisplay, CapturableWindow, CaptureAccessToken};
 use serde::{Deserialize, Serialize};
 use std::{sync::atomic::AtomicBool, time::SystemTime};
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{mpsc, watch, Mutex};
+
+use crate::{
+    audio::{
+        alignment::RecordingClock, audio_controller::AudioCommand, capture::CaptureReport,
+        segments::AudioSegment,
+    },
+    capture::thumbnail_cache::ThumbnailCache,
+};
+
+use super::{
+    app_settings::AppSettings, recording_session::RecordingSession, session_journal::SessionJournal,
+};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Auth {
@@ -10,20 +22,57 @@ pub struct Auth {
     pub locale: String,
 }
 
+#[derive(Clone)]
 pub enum CapturableSurface {
     CapturableDisplay(CapturableDisplay),
     CapturableWindow(CapturableWindow),
 }
 
+/// A pause of the recording, still going on while it has no end
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct RecordingPause {
+    pub start: SystemTime,
+    pub end: Option<SystemTime>,
+}
+
+/// When a step started and ended, its events uploaded with it
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct StepTime {
+    pub start: SystemTime,
+    pub end: SystemTime,
+}
+
 pub struct AppState {
     pub can_run_input: AtomicBool,
+    pub recording_paused: AtomicBool,
+    pub surface_lost: AtomicBool,
+    // the recording was paused when the surface was lost, and resumes with its replacement
+    pub paused_by_surface_loss: AtomicBool,
+    // the recording was started without an audio device, it only has its steps
+    pub steps_only: AtomicBool,
     pub capturable_surface: Mutex<Option<CapturableSurface>>,
     pub capture_token: Mutex<Option<CaptureAccessToken>>,
+    pub thumbnail_cache: Mutex<ThumbnailCache>,
 
-    pub can_run_audio: Mutex<mpsc::Sender<bool>>,
+    pub audio_commands: Mutex<mpsc::Sender<AudioCommand>>,
     pub audio_device: Mutex<Option<cpal::Device>>,
+    pub audio_capture_report: Mutex<Option<CaptureReport>>,
+    pub audio_segments: Mutex<Vec<AudioSegment>>,
+    // the sequences of the segments that failed to convert, their captures are converted again
+    pub failed_audio_segments: Mutex<Vec<usize>>,
+    // the audio of the last recording is still being converted and uploaded
+    pub audio_pending: watch::Sender<bool>,
 
     pub auth: Mutex<Option<Auth>>,
+    pub recording_session: Mutex<Option<RecordingSession>>,
+    pub session_journal: Mutex<Option<SessionJournal>>,
     pub recording_start_time: Mutex<Option<SystemTime>>,
     pub recording_end_time: Mutex<Option<SystemTime>>,
+    pub recording_pauses: Mutex<Vec<RecordingPause>>,
+    pub keystroke_times: Mutex<Vec<SystemTime>>,
+    pub step_times: Mutex<Vec<StepTime>>,
+    // the times of the inputs and of the captured audio, read on the same clock
+    pub recording_clock: RecordingClock,
+
+    pub settings: Mutex<AppSettings>,
 }
@@ -0,0 +1,109 @@
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use cpal::SupportedStreamConfig;
+use tauri::{AppHandle, Manager};
+
+use crate::audio::{
+    audio_settings::AudioSettings,
+    capture::{record_sample, CaptureReport},
+    convert_audio::convert_audio,
+};
+
+// samples recorded by this process, so that two recorded at once do not share their files
+static RECORDED_SAMPLES: AtomicUsize = AtomicUsize::new(0);
+
+/// A short sample recorded from a device outside of a recording, to be listened to or measured.
+/// Its file and the ones converted from it are removed once it is dropped, whatever failed.
+pub struct AudioSample {
+    pub filepath: PathBuf,
+    pub report: CaptureReport,
+    tmp_path: PathBuf,
+    // added to the names of its files
+    suffix: String,
+    converted: Vec<PathBuf>,
+}
+
+impl AudioSample {
+    /// Records `duration` from the device in the cache, as `name`
+    pub async fn record(
+        handle: &AppHandle,
+        audio_device: cpal::Device,
+        config: SupportedStreamConfig,
+        duration: Duration,
+        name: &str,
+    ) -> Result<AudioSample, String> {
+        let tmp_path = handle.path().app_cache_dir().map_err(|e| e.to_string())?;
+        let suffix = format!(
+            "{}_{}",
+            std::process::id(),
+            RECORDED_SAMPLES.fetch_add(1, Ordering::Relaxed)
+        );
+        let filepath = tmp_path.join(format!("{}_{}.wav", name, suffix));
+
+        let record_handle = handle.clone();
+        let record_path = filepath.clone();
+        let recorded = tauri::async_runtime::spawn_blocking(move || {
+            record_sample(
+                &record_handle,
+                &audio_device,
+                config,
+                duration,
+                &record_path,
+            )
+            .map_err(|e| e.to_string())
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+
+        // made before the result is looked at, so that what a failed recording wrote is removed too
+        let mut sample = AudioSample {
+            filepath,
+            report: CaptureReport::default(),
+            tmp_path,
+            suffix,
+            converted: Vec::new(),
+        };
+        sample.report = recorded?;
+        Ok(sample)
+    }
+
+    /// The sample converted with the settings as a recording would be, as a wav data url
+    pub async fn converted(
+        &mut self,
+        name: &str,
+        settings: &AudioSettings,
+    ) -> Result<String, String> {
+        let converted_path = self.tmp_path.join(format!("{}_{}.wav", name, self.suffix));
+        self.converted.push(converted_path.clone());
+
+        // the conversion reads and writes the files, it is kept off the async runtime
+        let filepath = self.filepath.clone();
+        let settings = settings.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            convert_audio(&filepath, &converted_path, &settings, &[], None)
+                .map_err(|e| e.to_string())?;
+            let wav_data = std::fs::read(&converted_path).map_err(|e| e.to_string())?;
+            Ok(format!(
+                "data:audio/wav;base64,{}",
+                rbase64::encode(&wav_data)
+            ))
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    }
+}
+
+impl Drop for AudioSample {
+    fn drop(&mut self) {
+        // the intermediate file of a failed conversion is left next to it
+        for filepath in std::iter::once(&self.filepath).chain(&self.converted) {
+            for filepath in [filepath.clone(), filepath.with_extension("mono.wav")] {
+                let _ = std::fs::remove_file(filepath);
+            }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use cpal::traits::DeviceTrait;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::audio::{
+    audio_settings::InputConfig,
+    input_config::{default_input_config, supported_input_configs},
+};
+
+use super::{
+    app_state::AppState, cmd_select_audio_start::select_audio_start,
+    selection_error::SelectionError,
+};
+
+#[derive(Clone, Serialize)]
+pub struct AudioConfigs {
+    pub configs: Vec<InputConfig>,
+    pub default: InputConfig,
+    // the config the device is opened with instead of its default one
+    pub selected: Option<InputConfig>,
+}
+
+fn find_device(name: &str) -> Result<cpal::Device, SelectionError> {
+    select_audio_start()?
+        .into_iter()
+        .find(|device| device.name().is_ok_and(|device_name| device_name == name))
+        .ok_or(SelectionError::AudioDeviceNotFound(name.to_string()))
+}
+
+/// Lists the sample rates and channel counts an input device supports
+#[tauri::command]
+pub async fn list_audio_configs(handle: AppHandle, name: String) -> Result<AudioConfigs, String> {
+    let state = handle
+        .try_state::<AppState>()
+        .ok_or("Failed to get AppState")?;
+
+    let device = find_device(&name)?;
+    let configs = supported_input_configs(&device).map_err(|e| e.to_string())?;
+    let default = default_input_config(&device).map_err(|e| e.to_string())?;
+    let selected = state
+        .settings
+        .lock()
+        .await
+        .audio
+        .input_configs
+        .get(&name)
+        .copied();
+
+    Ok(AudioConfigs {
+        configs,
+        default,
+        selected,
+    })
+}
+
+/// Opens the device with one of its supported configs from the next recording on,
+/// or with its default config again when none is given
+#[tauri::command]
+pub async fn set_audio_config(
+    handle: AppHandle,
+    name: String,
+    config: Option<InputConfig>,
+) -> Result<(), String> {
+    let state = handle
+        .try_state::<AppState>()
+        .ok_or("Failed to get AppState")?;
+
+    if let Some(config) = config {
+        let device = find_device(&name)?;
+        if !supported_input_configs(&device)
+            .map_err(|e| e.to_string())?
+            .contains(&config)
+        {
+            return Err(format!(
+                "{} does not support {} Hz with {} channels",
+                name, config.sample_rate, config.channels
+            ));
+        }
+    }
+
+    let mut settings = state.settings.lock().await;
+    match config {
+        Some(config) => settings.audio.input_configs.insert(name, config),
+        None => settings.audio.input_configs.remove(&name),
+    };
+    settings.save(&handle)
+}
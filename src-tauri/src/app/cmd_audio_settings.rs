@@ -0,0 +1,29 @@
+use tauri::{AppHandle, Manager};
+
+use crate::audio::audio_settings::AudioSettings;
+
+use super::app_state::AppState;
+
+#[tauri::command]
+pub async fn get_audio_settings(handle: AppHandle) -> Result<AudioSettings, String> {
+    let state = handle
+        .try_state::<AppState>()
+        .ok_or("Failed to get AppState")?;
+
+    let settings = state.settings.lock().await;
+    Ok(settings.audio.clone())
+}
+
+/// Replaces the audio settings, they apply from the next conversion on
+#[tauri::command]
+pub async fn set_audio_settings(handle: AppHandle, audio_settings: AudioSettings) -> Result<(), String> {
+    audio_settings.validate()?;
+
+    let state = handle
+        .try_state::<AppState>()
+        .ok_or("Failed to get AppState")?;
+
+    let mut settings = state.settings.lock().await;
+    settings.audio = audio_settings;
+    settings.save(&handle)
+}
@@ -10,6 +10,14 @@ pub async fn cancel_recording(handle: AppHandle) -> Result<bool, String> {
     
     let auth = state.auth.lock().await.clone().ok_or("No auth")?;
 
+    // the recording is dropped here whatever the server answers
+    if let Some(session) = state.recording_session.lock().await.take() {
+        session.remove()?;
+    }
+    *state.session_journal.lock().await = None;
+    state.audio_segments.lock().await.clear();
+    state.failed_audio_segments.lock().await.clear();
+
     let url = format!(
         "https://sniive.com/api/spaces/{}/delete-tutorial",
         auth.space_name
@@ -1,57 +1,161 @@
-use std::io::Read;
+use std::sync::atomic::Ordering;
 
 use tauri::{AppHandle, Manager};
 
-use crate::utils;
+use crate::audio::segments::{self, remove_captures, step_cues, SEGMENT_SECONDS};
 
-use super::app_state::AppState;
+use super::{app_state::AppState, session_journal::update_journal};
 
 #[tauri::command]
 pub async fn finish_recording(handle: AppHandle) -> Result<bool, String> {
-    let tmp_path = handle.path().app_cache_dir().map_err(|x| x.to_string())?;
-    let filepath = tmp_path.join("output.wav");
-    let upload_link = utils::get_upload_link(&handle, "wav").await.map_err(|x| x.to_string())?;
-        
-    let mut file = std::fs::File::open(filepath.clone()).map_err(|x| x.to_string())?;
-    let mut file_contents = Vec::new();
-    file.read_to_end(&mut file_contents).map_err(|x| x.to_string())?;
-    
-    let client = reqwest::Client::new();
-    let res = client
-        .put(&upload_link)
-        .header("Content-Type", "audio/wav")
-        .header("x-ms-blob-type", "BlockBlob")
-        .body(file_contents)
-        .send()
+    let state = handle
+        .try_state::<AppState>()
+        .ok_or("Failed to get AppState")?;
+    let session = state.recording_session.lock().await.clone().ok_or("No recording session")?;
+
+    // the audio task converts and uploads the end of the recording once it stopped
+    state
+        .audio_pending
+        .subscribe()
+        .wait_for(|pending| !pending)
         .await
         .map_err(|x| x.to_string())?;
 
-    if !res.status().is_success() {
-        return Ok(false);
+    // the segments that failed to convert are converted again from their captures, not left out
+    let failed_sequences = state.failed_audio_segments.lock().await.clone();
+    if !failed_sequences.is_empty() {
+        let audio_context = state
+            .session_journal
+            .lock()
+            .await
+            .as_ref()
+            .and_then(|journal| journal.audio_context.clone())
+            .ok_or("No audio context")?;
+        let alignment = state
+            .audio_capture_report
+            .lock()
+            .await
+            .as_ref()
+            .map(|audio_capture_report| audio_capture_report.alignment.clone());
+        let (converted, failed_sequences) = segments::convert_captures(
+            &handle,
+            &audio_context,
+            alignment.as_ref(),
+            &session.audio_dir(),
+            &failed_sequences,
+        )
+        .await
+        .map_err(|x| x.to_string())?;
+
+        let journal_segments = {
+            let mut audio_segments = state.audio_segments.lock().await;
+            audio_segments.extend(converted);
+            audio_segments.sort_by_key(|segment| segment.sequence);
+            audio_segments.clone()
+        };
+        *state.failed_audio_segments.lock().await = failed_sequences.clone();
+        let converted_segments = journal_segments.clone();
+        if update_journal(&handle, |journal| journal.audio_segments = journal_segments).await {
+            converted_segments.iter().for_each(remove_captures);
+        }
+        if !failed_sequences.is_empty() {
+            return Err(format!("Failed to convert the audio segments {:?}", failed_sequences));
+        }
+    }
+
+    // a recording without an audio device has no segments to upload
+    let steps_only = state.steps_only.load(Ordering::Relaxed);
+
+    // the last segment, and any that failed to upload during the recording
+    let mut audio_segments = state.audio_segments.lock().await.clone();
+    if audio_segments.is_empty() && !steps_only {
+        return Err("No recorded audio".to_string());
+    }
+    for segment in audio_segments.iter_mut() {
+        let uploaded = segments::upload_segment(&handle, segment).await.map_err(|x| x.to_string())?;
+        // links already obtained are kept for the next attempt
+        *state.audio_segments.lock().await = audio_segments.clone();
+        let journal_segments = audio_segments.clone();
+        update_journal(&handle, |journal| journal.audio_segments = journal_segments).await;
+        if !uploaded {
+            return Ok(false);
+        }
     }
-    std::fs::remove_file(filepath).map_err(|x| x.to_string())?;
 
-    let state = handle
-        .try_state::<AppState>()
-        .ok_or("Failed to get AppState")?;
-    
     let auth = state.auth.lock().await.clone().ok_or("No auth")?;
     let recording_start_time = state.recording_start_time.lock().await.ok_or("No recording start time")?;
     let recording_end_time = state.recording_end_time.lock().await.ok_or("No recording end time")?;
+    let audio_capture_report = state.audio_capture_report.lock().await.clone();
+    let recording_pauses = state
+        .recording_pauses
+        .lock()
+        .await
+        .iter()
+        .map(|pause| {
+            let epoch_millis = |time: std::time::SystemTime| {
+                time.duration_since(std::time::UNIX_EPOCH).map(|x| x.as_millis())
+            };
+            Ok(serde_json::json!({
+                "start": epoch_millis(pause.start).map_err(|x| x.to_string())?,
+                "end": pause.end.map(epoch_millis).transpose().map_err(|x| x.to_string())?,
+            }))
+        })
+        .collect::<Result<Vec<serde_json::Value>, String>>()?;
+
+    // the editor plays each step from its own part of the audio
+    let audio_context = state
+        .session_journal
+        .lock()
+        .await
+        .as_ref()
+        .and_then(|journal| journal.audio_context.clone());
+    let step_cues = match audio_context {
+        Some(audio_context) => step_cues(
+            &audio_context,
+            &state.recording_pauses.lock().await,
+            &state.step_times.lock().await,
+            &audio_segments,
+            audio_capture_report
+                .as_ref()
+                .map(|audio_capture_report| &audio_capture_report.alignment),
+        ),
+        None => Vec::new(),
+    };
+
+    // the metadata tells a recording without audio by its missing manifest,
+    // the audio keys it had before segments are in it, see the README
+    let audio_manifest = match audio_segments.first() {
+        Some(first_segment) => serde_json::json!({
+            "format": first_segment.files.format,
+            "segmentSeconds": SEGMENT_SECONDS,
+            "segments": audio_segments,
+            "stepCues": step_cues,
+        }),
+        None => serde_json::Value::Null,
+    };
 
     let url = format!(
         "https://sniive.com/api/spaces/{}/run-tutorial",
         auth.space_name
     );
 
+    let metadata = serde_json::json!({
+        "recordingStartTime": recording_start_time.duration_since(std::time::UNIX_EPOCH).map_err(|x| x.to_string())?.as_millis(),
+        "recordingEndTime": recording_end_time.duration_since(std::time::UNIX_EPOCH).map_err(|x| x.to_string())?.as_millis(),
+        "platform": std::env::consts::OS,
+        "version": env!("CARGO_PKG_VERSION"),
+        "recordingPauses": recording_pauses,
+        "hasAudio": audio_manifest.is_object(),
+        "audioCapture": audio_capture_report,
+        "audioManifest": audio_manifest,
+    });
+    tokio::fs::write(session.metadata_path(), metadata.to_string())
+        .await
+        .map_err(|x| x.to_string())?;
+
     let body = serde_json::json!({
         "access": auth.access,
-        "metadata": {
-            "recordingStartTime": recording_start_time.duration_since(std::time::UNIX_EPOCH).map_err(|x| x.to_string())?.as_millis(),
-            "recordingEndTime": recording_end_time.duration_since(std::time::UNIX_EPOCH).map_err(|x| x.to_string())?.as_millis(),
-            "platform": std::env::consts::OS,
-            "version": env!("CARGO_PKG_VERSION"),
-        }
+        "metadata": metadata,
     });
 
     let client = reqwest::Client::new();
@@ -62,6 +166,17 @@ pub async fn finish_recording(handle: AppHandle) -> Result<bool, String> {
         .send()
         .await
         .map_err(|x| x.to_string())?;
+    if !res.status().is_success() {
+        return Ok(false);
+    }
 
-    Ok(res.status().is_success())
-}
\ No newline at end of file
+    // everything is uploaded, the files of the session are not needed anymore
+    session.remove()?;
+    let mut recording_session = state.recording_session.lock().await;
+    if recording_session.as_ref().is_some_and(|current| current.id == session.id) {
+        *recording_session = None;
+        *state.session_journal.lock().await = None;
+        state.audio_segments.lock().await.clear();
+    }
+    Ok(true)
+}
@@ -0,0 +1,204 @@
+use std::{
+    path::Path,
+    sync::atomic::Ordering,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{
+    audio::segments::{recover_segments, remove_captures, repair_captured_audio},
+    upload::upload_controller::upload_step,
+};
+
+use super::{
+    app_state::AppState,
+    recording_session::RecordingSession,
+    session_journal::{update_journal, SessionJournal},
+};
+
+/// A recording the app was interrupted in, that can still be finished or cancelled
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterruptedRecording {
+    pub id: String,
+    pub space_name: String,
+    pub recording_start_time: u128,
+    pub steps: usize,
+    // steps that did not reach the server, uploaded again on recovery
+    pub pending_steps: usize,
+}
+
+/// The sessions on disk with a journal, but the one of the current recording
+async fn interrupted_sessions(
+    handle: &AppHandle,
+) -> Result<Vec<(RecordingSession, SessionJournal)>, String> {
+    let state = handle
+        .try_state::<AppState>()
+        .ok_or("Failed to get AppState")?;
+    let current_id = state
+        .recording_session
+        .lock()
+        .await
+        .as_ref()
+        .map(|session| session.id.clone());
+
+    Ok(RecordingSession::list(handle)?
+        .into_iter()
+        .filter(|session| Some(&session.id) != current_id.as_ref())
+        .filter_map(|session| {
+            let journal = SessionJournal::load(&session).ok()?;
+            Some((session, journal))
+        })
+        .collect())
+}
+
+/// Makes the audio of interrupted recordings readable again, once the app is launched
+pub async fn repair_interrupted_recordings(handle: &AppHandle) -> Result<(), String> {
+    for (session, _) in interrupted_sessions(handle).await? {
+        repair_captured_audio(&session.audio_dir()).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Lets the window offer to finish the interrupted recordings, once the app is launched
+pub async fn notify_interrupted_recordings(handle: &AppHandle) -> Result<(), String> {
+    let recordings = list_interrupted_recordings(handle.clone()).await?;
+    if !recordings.is_empty() {
+        handle
+            .emit("interrupted-recordings", recordings)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// The last time anything of the session was written, when the recording did not end by itself
+fn last_modified(dir: &Path) -> std::io::Result<Option<SystemTime>> {
+    let mut last = None;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let modified = if entry.file_type()?.is_dir() {
+            last_modified(&entry.path())?
+        } else {
+            Some(entry.metadata()?.modified()?)
+        };
+        last = last.max(modified);
+    }
+    Ok(last)
+}
+
+#[tauri::command]
+pub async fn list_interrupted_recordings(
+    handle: AppHandle,
+) -> Result<Vec<InterruptedRecording>, String> {
+    interrupted_sessions(&handle)
+        .await?
+        .into_iter()
+        .map(|(session, journal)| {
+            let steps = session.next_step()?;
+            Ok(InterruptedRecording {
+                id: session.id,
+                space_name: journal.space_name,
+                recording_start_time: journal
+                    .recording_start_time
+                    .duration_since(UNIX_EPOCH)
+                    .map_err(|e| e.to_string())?
+                    .as_millis(),
+                steps,
+                pending_steps: (0..steps)
+                    .filter(|step| !journal.uploaded_steps.contains(step))
+                    .count(),
+            })
+        })
+        .collect()
+}
+
+/// Makes an interrupted recording the current one again, with its audio converted and its steps uploaded,
+/// to be finished with `finish_recording` or discarded with `cancel_recording`
+#[tauri::command]
+pub async fn recover_recording(handle: AppHandle, id: String) -> Result<(), String> {
+    let state = handle
+        .try_state::<AppState>()
+        .ok_or("Failed to get AppState")?;
+    if state.can_run_input.load(Ordering::Relaxed) {
+        return Err("Cannot recover a recording while recording".to_string());
+    }
+
+    let session = RecordingSession::open(&handle, &id)?;
+    let mut journal = SessionJournal::load(&session)?;
+    // the recording is finished with the access of the space it was made for
+    let auth = state.auth.lock().await.clone().ok_or("No auth")?;
+    if auth.space_name != journal.space_name {
+        return Err(format!(
+            "The recording was made for the space '{}'",
+            journal.space_name
+        ));
+    }
+
+    // a recording that did not stop ended with the last thing it wrote
+    let recording_end_time = match journal.recording_end_time {
+        Some(recording_end_time) => recording_end_time,
+        None => last_modified(&session.dir)
+            .map_err(|e| e.to_string())?
+            .unwrap_or(journal.recording_start_time),
+    };
+    journal.recording_end_time = Some(recording_end_time);
+    for pause in journal.recording_pauses.iter_mut() {
+        pause.end.get_or_insert(recording_end_time);
+    }
+
+    // the conversion reads the keystrokes and pauses of the recording from the state
+    state.recording_paused.store(false, Ordering::Relaxed);
+    state.steps_only.store(journal.steps_only, Ordering::Relaxed);
+    *state.recording_start_time.lock().await = Some(journal.recording_start_time);
+    *state.recording_end_time.lock().await = Some(recording_end_time);
+    *state.recording_pauses.lock().await = journal.recording_pauses.clone();
+    *state.keystroke_times.lock().await = journal.keystroke_times.clone();
+    *state.step_times.lock().await = journal.step_times.clone();
+    *state.audio_capture_report.lock().await = journal.audio_capture_report.clone();
+
+    let (audio_segments, failed_sequences) = match &journal.audio_context {
+        // a recording that did not stop has no capture report, its keystrokes are placed by the clock
+        Some(context) => recover_segments(
+            &handle,
+            context,
+            journal
+                .audio_capture_report
+                .as_ref()
+                .map(|audio_capture_report| &audio_capture_report.alignment),
+            &session.audio_dir(),
+            journal.audio_segments.clone(),
+        )
+        .await
+        .map_err(|e| e.to_string())?,
+        None => (Vec::new(), Vec::new()),
+    };
+    *state.audio_segments.lock().await = audio_segments.clone();
+    *state.failed_audio_segments.lock().await = failed_sequences;
+    journal.audio_segments = audio_segments;
+
+    journal.save(&session).await?;
+    journal.audio_segments.iter().for_each(remove_captures);
+    let uploaded_steps = journal.uploaded_steps.clone();
+    *state.recording_session.lock().await = Some(session.clone());
+    *state.session_journal.lock().await = Some(journal);
+
+    // the steps that did not reach the server go again, after the others
+    for step in (0..session.next_step()?).filter(|step| !uploaded_steps.contains(step)) {
+        let result_string =
+            tokio::fs::read_to_string(session.steps_dir().join(format!("{}.json", step)))
+                .await
+                .map_err(|e| e.to_string())?;
+        if upload_step(&handle, result_string)
+            .await
+            .map_err(|e| e.to_string())?
+        {
+            update_journal(&handle, |journal| {
+                journal.uploaded_steps.insert(step);
+            })
+            .await;
+        }
+    }
+    Ok(())
+}
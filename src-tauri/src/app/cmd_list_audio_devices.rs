@@ -0,0 +1,20 @@
+use cpal::traits::DeviceTrait;
+
+use super::{
+    cmd_select_audio::AudioDevice, cmd_select_audio_start::select_audio_start,
+    selection_error::SelectionError,
+};
+
+#[tauri::command]
+pub async fn list_audio_devices() -> Result<Vec<AudioDevice>, SelectionError> {
+    let devices = select_audio_start()?;
+
+    Ok(devices
+        .iter()
+        .enumerate()
+        .map(|(id, device)| AudioDevice {
+            name: device.name().unwrap_or("Unknown".to_string()),
+            id,
+        })
+        .collect())
+}
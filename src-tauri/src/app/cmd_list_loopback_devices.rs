@@ -0,0 +1,20 @@
+use cpal::traits::DeviceTrait;
+
+use crate::audio::loopback::loopback_devices;
+
+use super::{cmd_select_audio::AudioDevice, selection_error::SelectionError};
+
+/// Sources of system audio, selected by name in the audio settings
+#[tauri::command]
+pub async fn list_loopback_devices() -> Result<Vec<AudioDevice>, SelectionError> {
+    let devices = loopback_devices().map_err(SelectionError::AudioHost)?;
+
+    Ok(devices
+        .iter()
+        .enumerate()
+        .map(|(id, device)| AudioDevice {
+            name: device.name().unwrap_or("Unknown".to_string()),
+            id,
+        })
+        .collect())
+}
@@ -0,0 +1,21 @@
+use tauri::AppHandle;
+
+use super::{
+    cmd_select_surface_start::{select_surface_start, Surface, Thumbnails},
+    selection_error::SelectionError,
+};
+
+/// Lists the capturable windows and displays without opening the picker,
+/// with thumbnails fitting in a `thumbnail_size` square when it is given
+#[tauri::command]
+pub async fn list_surfaces(
+    handle: AppHandle,
+    thumbnail_size: Option<u32>,
+) -> Result<Vec<Surface>, SelectionError> {
+    let thumbnails = match thumbnail_size {
+        Some(size) => Thumbnails::Fit(size),
+        None => Thumbnails::None,
+    };
+
+    select_surface_start(&handle, thumbnails).await
+}
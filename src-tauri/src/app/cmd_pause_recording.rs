@@ -0,0 +1,57 @@
+use std::sync::atomic::Ordering;
+
+use tauri::{AppHandle, Manager};
+
+use crate::{audio::audio_controller::AudioCommand, utils::notify_recording_status};
+
+use super::{
+    app_state::{AppState, RecordingPause},
+    session_journal::update_journal,
+};
+
+/// Suspends the input and audio capture, the recording goes on from there on resume
+#[tauri::command]
+pub async fn pause_recording(handle: AppHandle) -> Result<(), String> {
+    pause(&handle).await.map(|_| ())
+}
+
+/// Pauses the recording, false if there was no running recording to pause
+pub async fn pause(handle: &AppHandle) -> Result<bool, String> {
+    let state = handle
+        .try_state::<AppState>()
+        .ok_or("Failed to get AppState")?;
+
+    if !state.can_run_input.load(Ordering::Relaxed) {
+        return Ok(false);
+    }
+    // claimed before notifying, a pause of the user and one of a lost surface do not both start
+    if state
+        .recording_paused
+        .compare_exchange(false, true, Ordering::Relaxed, Ordering::Relaxed)
+        .is_err()
+    {
+        return Ok(false);
+    }
+
+    let notified = notify_recording_status(handle, "pause").await.map_err(|x| x.to_string());
+    if !matches!(notified, Ok(true)) {
+        state.recording_paused.store(false, Ordering::Relaxed);
+        notified?;
+        return Err("Failed to notify recording status".to_string());
+    }
+
+    state.recording_pauses.lock().await.push(RecordingPause {
+        start: state.recording_clock.now(),
+        end: None,
+    });
+    let recording_pauses = state.recording_pauses.lock().await.clone();
+    update_journal(handle, |journal| journal.recording_pauses = recording_pauses).await;
+    state
+        .audio_commands
+        .lock()
+        .await
+        .send(AudioCommand::Pause)
+        .await
+        .map_err(|_| "Failed to send to audio")?;
+    Ok(true)
+}
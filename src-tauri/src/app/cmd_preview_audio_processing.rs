@@ -0,0 +1,82 @@
+use std::{sync::atomic::Ordering, time::Duration};
+
+use cpal::traits::DeviceTrait;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::audio::{
+    audio_settings::{AudioSettings, DspSettings},
+    input_config::input_stream_config,
+};
+
+use super::{app_state::AppState, audio_sample::AudioSample};
+
+const DEFAULT_PREVIEW_SECONDS: u64 = 5;
+const MAX_PREVIEW_SECONDS: u64 = 15;
+
+/// The same sample without and with the clean-up stages, as wav data urls
+#[derive(Clone, Serialize)]
+pub struct AudioPreview {
+    pub original: String,
+    pub processed: String,
+}
+
+/// Records a short sample from the selected microphone and processes it with the given
+/// settings (or the saved ones), so that the clean-up stages can be compared by ear
+#[tauri::command]
+pub async fn preview_audio_processing(
+    handle: AppHandle,
+    audio_settings: Option<AudioSettings>,
+    seconds: Option<u64>,
+) -> Result<AudioPreview, String> {
+    let state = handle
+        .try_state::<AppState>()
+        .ok_or("Failed to get AppState")?;
+    if state.can_run_input.load(Ordering::Relaxed) {
+        return Err("Cannot preview the audio while recording".to_string());
+    }
+
+    let audio_settings = match audio_settings {
+        Some(audio_settings) => {
+            audio_settings.validate()?;
+            audio_settings
+        }
+        None => state.settings.lock().await.audio.clone(),
+    };
+    let audio_device = state
+        .audio_device
+        .lock()
+        .await
+        .clone()
+        .ok_or("No audio device")?;
+    let input_configs = state.settings.lock().await.audio.input_configs.clone();
+    let device_name = audio_device.name().map_err(|e| e.to_string())?;
+    let config = input_stream_config(&audio_device, input_configs.get(&device_name))
+        .map_err(|e| e.to_string())?;
+
+    let duration = Duration::from_secs(
+        seconds
+            .unwrap_or(DEFAULT_PREVIEW_SECONDS)
+            .clamp(1, MAX_PREVIEW_SECONDS),
+    );
+    let mut sample =
+        AudioSample::record(&handle, audio_device, config, duration, "preview_sample").await?;
+
+    // both versions are normalized the same way and keep their silences, only the clean-up differs
+    let mut processed_settings = audio_settings;
+    processed_settings.silence.trim_silence = false;
+    processed_settings.silence.shorten_pauses = false;
+    let original_settings = AudioSettings {
+        dsp: DspSettings::default(),
+        ..processed_settings.clone()
+    };
+
+    Ok(AudioPreview {
+        original: sample
+            .converted("preview_original", &original_settings)
+            .await?,
+        processed: sample
+            .converted("preview_processed", &processed_settings)
+            .await?,
+    })
+}
@@ -0,0 +1,99 @@
+use cpal::traits::{DeviceTrait, HostTrait};
+use crabgrab::prelude::{CapturableContent, CapturableContentFilter, CaptureStream};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::{
+    app_settings::LastSelection,
+    app_state::{AppState, CapturableSurface},
+    cmd_select_surface::SurfaceOutput,
+};
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RestoredSelection {
+    pub surface: Option<SurfaceOutput>,
+    pub audio_device: Option<String>,
+}
+
+/// Matches the last persisted selection against the current surfaces and input devices
+pub async fn restore_selection(handle: &AppHandle) -> Result<(), String> {
+    let state = handle
+        .try_state::<AppState>()
+        .ok_or("Failed to get AppState")?;
+    let last_selection = state.settings.lock().await.last_selection.clone();
+
+    if let Some(remembered_surface) = last_selection.surface {
+        // never prompt for capture access at startup, the picker will do it
+        if let Some(token) = CaptureStream::test_access(false) {
+            let content = CapturableContent::new(CapturableContentFilter::EVERYTHING_NORMAL)
+                .await
+                .map_err(|_| "Failed to get capturable content")?;
+            if let Some(surface) = remembered_surface.find(&content) {
+                *state.capture_token.lock().await = Some(token);
+                *state.capturable_surface.lock().await = Some(surface);
+            }
+        }
+    }
+
+    if let Some(device_name) = last_selection.audio_device {
+        let host = cpal::default_host();
+        let device = host
+            .input_devices()
+            .map_err(|e| e.to_string())?
+            .find(|device| device.name().map_or(false, |name| name == device_name));
+        if let Some(device) = device {
+            *state.audio_device.lock().await = Some(device);
+        }
+    }
+
+    // the main window may have asked before the restoration finished
+    let restored_selection = get_restored_selection(handle.clone()).await?;
+    handle
+        .emit("selection-restored", restored_selection)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_restored_selection(handle: AppHandle) -> Result<RestoredSelection, String> {
+    let state = handle
+        .try_state::<AppState>()
+        .ok_or("Failed to get AppState")?;
+
+    let surface = state
+        .capturable_surface
+        .lock()
+        .await
+        .as_ref()
+        .map(|surface| SurfaceOutput {
+            title: match surface {
+                CapturableSurface::CapturableWindow(window) => window.title(),
+                CapturableSurface::CapturableDisplay(_) => "Monitor".to_string(),
+            },
+            thumbnail: String::new(),
+        });
+    let audio_device = state
+        .audio_device
+        .lock()
+        .await
+        .as_ref()
+        .map(|device| device.name().unwrap_or("Unknown".to_string()));
+
+    Ok(RestoredSelection {
+        surface,
+        audio_device,
+    })
+}
+
+#[tauri::command]
+pub async fn clear_restored_selection(handle: AppHandle) -> Result<(), String> {
+    let state = handle
+        .try_state::<AppState>()
+        .ok_or("Failed to get AppState")?;
+
+    *state.capturable_surface.lock().await = None;
+    *state.audio_device.lock().await = None;
+
+    let mut settings = state.settings.lock().await;
+    settings.last_selection = LastSelection::default();
+    settings.save(&handle)
+}
@@ -0,0 +1,53 @@
+use std::sync::atomic::Ordering;
+
+use tauri::{AppHandle, Manager};
+
+use crate::{audio::audio_controller::AudioCommand, utils::notify_recording_status};
+
+use super::{app_state::AppState, session_journal::update_journal};
+
+#[tauri::command]
+pub async fn resume_recording(handle: AppHandle) -> Result<(), String> {
+    resume(&handle).await.map(|_| ())
+}
+
+/// Resumes the recording, false if there was no paused recording to resume
+pub async fn resume(handle: &AppHandle) -> Result<bool, String> {
+    let state = handle
+        .try_state::<AppState>()
+        .ok_or("Failed to get AppState")?;
+
+    if !state.can_run_input.load(Ordering::Relaxed) {
+        return Ok(false);
+    }
+    // claimed before notifying, two resumes do not both end the pause
+    if state
+        .recording_paused
+        .compare_exchange(true, false, Ordering::Relaxed, Ordering::Relaxed)
+        .is_err()
+    {
+        return Ok(false);
+    }
+
+    let notified = notify_recording_status(handle, "resume").await.map_err(|x| x.to_string());
+    if !matches!(notified, Ok(true)) {
+        state.recording_paused.store(true, Ordering::Relaxed);
+        notified?;
+        return Err("Failed to notify recording status".to_string());
+    }
+
+    // the pause ends on the timeline before the audio starts again
+    if let Some(pause) = state.recording_pauses.lock().await.last_mut() {
+        pause.end.get_or_insert(state.recording_clock.now());
+    }
+    let recording_pauses = state.recording_pauses.lock().await.clone();
+    update_journal(handle, |journal| journal.recording_pauses = recording_pauses).await;
+    state
+        .audio_commands
+        .lock()
+        .await
+        .send(AudioCommand::Resume)
+        .await
+        .map_err(|_| "Failed to send to audio")?;
+    Ok(true)
+}
@@ -1,26 +1,35 @@
 use cpal::traits::DeviceTrait;
 use tauri::{AppHandle, Manager};
 
-use super::app_state::AppState;
+use super::{
+    app_settings::remember_audio_device, app_state::AppState, selection_error::SelectionError,
+};
 
 pub async fn select_audio_close(
     handle: &AppHandle,
     device_option: Option<cpal::Device>,
-) -> Result<String, String> {
+) -> Result<String, SelectionError> {
     let state = handle
         .try_state::<AppState>()
-        .ok_or("Failed to get AppState")?;
+        .ok_or(SelectionError::AppState)?;
 
     match device_option {
         Some(device) => {
-            let name = device.name().map_err(|e| e.to_string())?;
-            let mut audio_device = state.audio_device.lock().await;
-            *audio_device = Some(device);
+            let name = device
+                .name()
+                .map_err(|e| SelectionError::AudioHost(e.to_string()))?;
+            *state.audio_device.lock().await = Some(device);
+            // the selection is made even if it cannot be restored next time
+            if let Err(e) = remember_audio_device(handle, Some(name.clone())).await {
+                eprintln!("Failed to remember the audio device: {}", e);
+            }
             Ok(name)
         }
         None => {
-            let mut audio_device = state.audio_device.lock().await;
-            *audio_device = None;
+            *state.audio_device.lock().await = None;
+            if let Err(e) = remember_audio_device(handle, None).await {
+                eprintln!("Failed to remember the audio device: {}", e);
+            }
             Ok("None".to_string())
         }
     }
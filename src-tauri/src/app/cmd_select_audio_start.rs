@@ -1,20 +1,15 @@
 use cpal::traits::{DeviceTrait, HostTrait};
 
-pub fn select_audio_start() -> Result<Vec<cpal::Device>, String> {
+use super::selection_error::SelectionError;
+
+pub fn select_audio_start() -> Result<Vec<cpal::Device>, SelectionError> {
     let host = cpal::default_host();
-    let devices = host.input_devices().map_err(|x| x.to_string())?;
+    let devices = host
+        .input_devices()
+        .map_err(|x| SelectionError::AudioHost(x.to_string()))?;
+    // every sample format is converted by the capture, a device only needs to have an input config
     let selected_devices = devices
-        .filter_map(|device| {
-            if let Ok(config) = device.default_input_config() {
-                return match config.sample_format() {
-                    cpal::SampleFormat::I16 => Some(device),
-                    cpal::SampleFormat::I32 => Some(device),
-                    cpal::SampleFormat::F32 => Some(device),
-                    _ => None,
-                };
-            }
-            None
-        })
+        .filter(|device| device.default_input_config().is_ok())
         .collect::<Vec<cpal::Device>>();
 
     Ok(selected_devices)
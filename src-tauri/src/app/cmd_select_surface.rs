@@ -1,13 +1,20 @@
+use futures::{stream::FuturesUnordered, StreamExt};
 use serde::{Deserialize, Serialize};
-use tauri::{AppHandle, Emitter, WebviewWindowBuilder};
+use tauri::{AppHandle, Emitter, Manager, WebviewWindowBuilder};
 
 use crate::{app::cmd_select_surface_close::select_surface_close, utils::wait_for_event};
 
 use super::{
+    app_state::AppState,
     cmd_select_surface_close::SurfaceSelection,
-    cmd_select_surface_start::{select_surface_start, Surface, SurfaceType},
+    cmd_select_surface_start::{
+        select_surface_options, surface_thumbnail, Surface, SurfaceType, Thumbnails,
+    },
 };
 
+// the picker shows thumbnails at most 80px high, keep some margin for high dpi screens
+const PICKER_THUMBNAIL_SIZE: u32 = 320;
+
 #[derive(Clone, Copy, Serialize, Deserialize)]
 struct SelectedSurface {
     id: isize,
@@ -20,7 +27,13 @@ enum SelectedSurfaceResult {
     Selected(SelectedSurface), // Selection process was completed
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
+struct SurfaceThumbnail {
+    id: isize,
+    thumbnail: Option<String>, // None when the surface could not be captured
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SurfaceOutput {
     pub title: String,
     pub thumbnail: String,
@@ -34,7 +47,7 @@ pub enum SurfaceOutputResult {
 
 #[tauri::command]
 pub async fn select_surface(handle: AppHandle) -> Result<SurfaceOutputResult, String> {
-    let thumbnails = select_surface_start(&handle).await?;
+    let (token, surfaces_options) = select_surface_options(&handle).await?;
 
     let select_surface_builder = WebviewWindowBuilder::new(
         &handle,
@@ -57,9 +70,9 @@ pub async fn select_surface(handle: AppHandle) -> Result<SurfaceOutputResult, St
     #[cfg(target_os = "macos")]
     let select_surface_window = select_surface_builder.build().map_err(|e| e.to_string())?;
 
-    let surfaces: Vec<Surface> = thumbnails
-        .into_iter()
-        .filter_map(|thumbnail| thumbnail)
+    let surfaces: Vec<Surface> = surfaces_options
+        .iter()
+        .map(|surface_option| surface_option.surface(None))
         .collect();
 
     wait_for_event(&select_surface_window, "ready".to_string())
@@ -70,6 +83,33 @@ pub async fn select_surface(handle: AppHandle) -> Result<SurfaceOutputResult, St
         .emit("surfaces", surfaces.clone())
         .map_err(|e| e.to_string())?;
 
+    // thumbnails are sent one by one as they finish, while the user is already choosing
+    let thumbnail_handle = handle.clone();
+    let thumbnail_window = select_surface_window.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut pending_thumbnails = surfaces_options
+            .iter()
+            .map(|surface_option| async {
+                let thumbnail = surface_thumbnail(
+                    &thumbnail_handle,
+                    token,
+                    surface_option,
+                    Thumbnails::Fit(PICKER_THUMBNAIL_SIZE),
+                )
+                .await;
+                SurfaceThumbnail {
+                    id: surface_option.id(),
+                    thumbnail,
+                }
+            })
+            .collect::<FuturesUnordered<_>>();
+
+        while let Some(surface_thumbnail) = pending_thumbnails.next().await {
+            // the window may have been closed in the meantime
+            let _ = thumbnail_window.emit("surface-thumbnail", surface_thumbnail);
+        }
+    });
+
     let evt = wait_for_event(&select_surface_window, "selected".to_string())
         .await
         .ok_or("No selected event")?;
@@ -84,11 +124,16 @@ pub async fn select_surface(handle: AppHandle) -> Result<SurfaceOutputResult, St
             return Ok(SurfaceOutputResult::SelectedNone);
         }
         SelectedSurfaceResult::Selected(payload) => {
+            let display_identity = surfaces
+                .iter()
+                .find(|surface| surface.id == payload.id)
+                .and_then(|surface| surface.display_identity.clone());
             select_surface_close(
                 &handle,
                 Some(SurfaceSelection {
                     id: payload.id,
                     surface_type: payload.surface_type,
+                    display_identity,
                 }),
             )
             .await?;
@@ -96,9 +141,17 @@ pub async fn select_surface(handle: AppHandle) -> Result<SurfaceOutputResult, St
                 .into_iter()
                 .find(|surface| surface.id == payload.id)
                 .ok_or("No surface selected")?;
+            let thumbnail = handle
+                .try_state::<AppState>()
+                .ok_or("Failed to get AppState")?
+                .thumbnail_cache
+                .lock()
+                .await
+                .get(payload.id, PICKER_THUMBNAIL_SIZE)
+                .unwrap_or_default();
             return Ok(SurfaceOutputResult::Selected(SurfaceOutput {
                 title: selected_surface.title,
-                thumbnail: selected_surface.thumbnail,
+                thumbnail,
             }));
         }
     }
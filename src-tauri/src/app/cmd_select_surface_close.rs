@@ -1,72 +1,113 @@
+use std::sync::atomic::Ordering;
+
 use crabgrab::prelude::{CapturableContent, CapturableContentFilter};
-use tauri::{AppHandle, Manager};
+use tauri::{AppHandle, Emitter, Manager};
 
 #[cfg(target_os = "windows")]
 use crabgrab::platform::windows::WindowsCapturableWindowExt;
 #[cfg(target_os = "macos")]
 use crabgrab::platform::macos::MacosCapturableWindowExt;
 
-use crate::utils::display_id;
+use crate::capture::display_identity::{display_id, find_display, DisplayIdentity};
 
 use super::{
+    app_settings::remember_surface,
     app_state::{AppState, CapturableSurface},
-    cmd_select_surface_start::SurfaceType,
+    cmd_resume_recording::resume,
+    cmd_select_surface_start::{capture_access, SurfaceType},
+    selection_error::SelectionError,
 };
 
 pub struct SurfaceSelection {
     pub id: isize,
     pub surface_type: SurfaceType,
+    // identity of the listed display, used when its id no longer matches
+    pub display_identity: Option<DisplayIdentity>,
 }
 
+/// Stores the selected surface in the AppState and returns its title
 pub async fn select_surface_close(
     handle: &AppHandle,
     surface_selection: Option<SurfaceSelection>,
-) -> Result<(), String> {
+) -> Result<String, SelectionError> {
     let state = handle
         .try_state::<AppState>()
-        .ok_or("Failed to get AppState")?;
+        .ok_or(SelectionError::AppState)?;
 
-    match surface_selection {
+    let title = match surface_selection {
         Some(surface_output) => {
             let surface_id = surface_output.id;
             let surface_type = surface_output.surface_type;
 
+            // the selection may not come from select_surface_start, which asks for access
+            if state.capture_token.lock().await.is_none() {
+                capture_access(handle).await?;
+            }
+
             let filter = CapturableContentFilter::EVERYTHING_NORMAL;
             let content = CapturableContent::new(filter)
                 .await
-                .map_err(|_| "Failed to get capturable content")?;
-            match surface_type {
+                .map_err(|_| SelectionError::CapturableContent)?;
+            let surface = match surface_type {
                 SurfaceType::Window => {
                     #[cfg(target_os = "windows")]
                     let window = content
                         .windows()
                         .find(|window| window.get_window_handle().0 == surface_id)
-                        .ok_or("Window not found")?;
+                        .ok_or(SelectionError::SurfaceNotFound(surface_id))?;
                     #[cfg(target_os = "macos")]
                     let window = content
                         .windows()
                         .find(|window| window.get_window_id() as isize == surface_id)
-                        .ok_or("Window not found")?;
-                    
-                    let mut capturable_surface = state.capturable_surface.lock().await;
-                    *capturable_surface = Some(CapturableSurface::CapturableWindow(window));
-                    return Ok(());
+                        .ok_or(SelectionError::SurfaceNotFound(surface_id))?;
+
+                    CapturableSurface::CapturableWindow(window)
                 }
                 SurfaceType::Display => {
-                    let display = content
+                    let display = match content
                         .displays()
                         .find(|display| display_id(display) == surface_id)
-                        .ok_or("Display not found")?;
-                    let mut capturable_surface = state.capturable_surface.lock().await;
-                    *capturable_surface = Some(CapturableSurface::CapturableDisplay(display));
-                    return Ok(());
+                    {
+                        Some(display) => Some(display),
+                        None => surface_output
+                            .display_identity
+                            .as_ref()
+                            .and_then(|identity| find_display(content.displays(), identity)),
+                    }
+                    .ok_or(SelectionError::SurfaceNotFound(surface_id))?;
+
+                    CapturableSurface::CapturableDisplay(display)
                 }
+            };
+
+            let title = match &surface {
+                CapturableSurface::CapturableWindow(window) => window.title(),
+                CapturableSurface::CapturableDisplay(_) => "Monitor".to_string(),
+            };
+            *state.capturable_surface.lock().await = Some(surface.clone());
+            // the selection is made even if it cannot be restored next time
+            if let Err(e) = remember_surface(handle, Some(&surface)).await {
+                eprintln!("Failed to remember the surface: {}", e);
             }
+            title
         }
         None => {
-            let mut capturable_surface = state.capturable_surface.lock().await;
-            *capturable_surface = None;
-            return Ok(());
+            *state.capturable_surface.lock().await = None;
+            if let Err(e) = remember_surface(handle, None).await {
+                eprintln!("Failed to remember the surface: {}", e);
+            }
+            "None".to_string()
+        }
+    };
+
+    // a surface lost mid-recording is replaced or given up on, either way the recording goes on
+    if state.surface_lost.swap(false, Ordering::Relaxed) {
+        if state.paused_by_surface_loss.swap(false, Ordering::Relaxed) {
+            if let Err(e) = resume(handle).await {
+                eprintln!("Failed to resume the recording: {}", e);
+            }
         }
+        let _ = handle.emit("surface-restored", title.clone());
     }
+    Ok(title)
 }
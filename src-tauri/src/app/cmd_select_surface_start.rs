@@ -1,8 +1,8 @@
 use std::time::Duration;
 
 use crabgrab::prelude::{
-    take_screenshot, CapturableContent, CapturableContentFilter, CaptureConfig,
-    CapturePixelFormat, CaptureStream, FrameBitmap, VideoFrameBitmap,
+    take_screenshot, CapturableContent, CapturableContentFilter, CaptureAccessToken,
+    CaptureConfig, CapturePixelFormat, CaptureStream, FrameBitmap, VideoFrameBitmap,
 };
 use futures::future::join_all;
 use serde::{Deserialize, Serialize};
@@ -14,9 +14,9 @@ use crabgrab::platform::windows::WindowsCapturableWindowExt;
 #[cfg(target_os = "macos")]
 use crabgrab::platform::macos::MacosCapturableWindowExt;
 
-use crate::utils;
+use crate::{capture::display_identity::DisplayIdentity, utils};
 
-use super::app_state::AppState;
+use super::{app_state::AppState, selection_error::SelectionError};
 
 #[derive(Clone, Copy, Serialize, Deserialize)]
 pub enum SurfaceType {
@@ -25,11 +25,12 @@ pub enum SurfaceType {
 }
 
 #[derive(Clone)]
-struct SurfaceOption {
+pub struct SurfaceOption {
     id: isize,
     program: String,
     title: String,
     surface_type: SurfaceType,
+    display_identity: Option<DisplayIdentity>,
     config: CaptureConfig,
 }
 
@@ -39,27 +40,45 @@ pub struct Surface {
     pub id: isize,
     pub title: String,
     pub program: String,
-    pub thumbnail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<String>,
+    #[serde(skip)]
+    pub display_identity: Option<DisplayIdentity>,
 }
 
-pub async fn select_surface_start(handle: &AppHandle) -> Result<Vec<Option<Surface>>, &str> {
+#[derive(Clone, Copy)]
+pub enum Thumbnails {
+    None,     // Surfaces are only listed
+    Fit(u32), // Screenshot downscaled to fit in a square of this size
+}
+
+/// Returns the capture token, asking the user for access if it was not granted yet
+pub async fn capture_access(handle: &AppHandle) -> Result<CaptureAccessToken, SelectionError> {
     let token = match CaptureStream::test_access(false) {
         Some(token) => token,
         None => CaptureStream::request_access(false)
             .await
-            .expect("Expected capture access"),
+            .ok_or(SelectionError::CaptureAccessDenied)?,
     };
 
     let state = handle
         .try_state::<AppState>()
-        .ok_or("Failed to get AppState")?;
+        .ok_or(SelectionError::AppState)?;
     let mut capture_token = state.capture_token.lock().await;
     *capture_token = Some(token);
+    Ok(token)
+}
+
+/// Lists the capturable surfaces without taking any screenshot
+pub async fn select_surface_options(
+    handle: &AppHandle,
+) -> Result<(CaptureAccessToken, Vec<SurfaceOption>), SelectionError> {
+    let token = capture_access(handle).await?;
 
     let filter = CapturableContentFilter::EVERYTHING_NORMAL;
     let content = CapturableContent::new(filter)
         .await
-        .map_err(|_| "Failed to get capturable content")?;
+        .map_err(|_| SelectionError::CapturableContent)?;
 
     let mut surfaces_options: Vec<SurfaceOption> = [].to_vec();
     let self_pid = std::process::id() as i32;
@@ -86,6 +105,7 @@ pub async fn select_surface_start(handle: &AppHandle) -> Result<Vec<Option<Surfa
                     program,
                     title,
                     surface_type: SurfaceType::Window,
+                    display_identity: None,
                     config,
                 });
             }
@@ -95,7 +115,8 @@ pub async fn select_surface_start(handle: &AppHandle) -> Result<Vec<Option<Surfa
         .displays()
         .filter(|display| display.rect().size.width > 0.0 && display.rect().size.height > 0.0)
         .for_each(|display| {
-            let id = utils::display_id(&display);
+            let display_identity = DisplayIdentity::of(&display);
+            let id = display_identity.id();
             let title = "Monitor".to_string();
             let config = CaptureConfig::with_display(display, CapturePixelFormat::Bgra8888);
             return surfaces_options.push(SurfaceOption {
@@ -103,46 +124,98 @@ pub async fn select_surface_start(handle: &AppHandle) -> Result<Vec<Option<Surfa
                 title,
                 program: "Display".to_string(),
                 surface_type: SurfaceType::Display,
+                display_identity: Some(display_identity),
                 config,
             });
         });
 
-    let thumbnails = join_all(
-        surfaces_options
-            .into_iter()
-            .map(|surface_option| async move {
-                let screenshot = match timeout(
-                    Duration::from_secs(1),
-                    take_screenshot(token, surface_option.config),
-                )
-                .await
-                {
-                    Ok(Ok(screenshot)) => screenshot,
-                    _ => return None,
-                };
-
-                let image_bitmap_bgra8888 = match screenshot.get_bitmap() {
-                    Ok(FrameBitmap::BgraUnorm8x4(image_bitmap)) => image_bitmap,
-                    _ => return None,
-                };
-
-                let image_base64 = match utils::make_base64_jpeg_from_bitmap(
-                    &image_bitmap_bgra8888,
-                ) {
-                    Ok(image_base64) => image_base64,
-                    _ => return None,
-                };
-
-                Some(Surface {
-                    id: surface_option.id,
-                    title: surface_option.title,
-                    program: surface_option.program,
-                    surface_type: surface_option.surface_type,
-                    thumbnail: image_base64,
-                })
-            }),
+    Ok((token, surfaces_options))
+}
+
+/// Screenshots a surface and encodes it, reusing recent thumbnails from the cache
+pub async fn surface_thumbnail(
+    handle: &AppHandle,
+    token: CaptureAccessToken,
+    surface_option: &SurfaceOption,
+    thumbnails: Thumbnails,
+) -> Option<String> {
+    let max_size = match thumbnails {
+        Thumbnails::None => return None,
+        Thumbnails::Fit(size) => size,
+    };
+
+    let state = handle.try_state::<AppState>()?;
+    if let Some(thumbnail) = state
+        .thumbnail_cache
+        .lock()
+        .await
+        .get(surface_option.id, max_size)
+    {
+        return Some(thumbnail);
+    }
+
+    let screenshot = match timeout(
+        Duration::from_secs(1),
+        take_screenshot(token, surface_option.config.clone()),
     )
+    .await
+    {
+        Ok(Ok(screenshot)) => screenshot,
+        _ => return None,
+    };
+
+    let image_bitmap_bgra8888 = match screenshot.get_bitmap() {
+        Ok(FrameBitmap::BgraUnorm8x4(image_bitmap)) => image_bitmap,
+        _ => return None,
+    };
+
+    let image_base64 =
+        utils::make_base64_jpeg_from_bitmap(&image_bitmap_bgra8888, Some(max_size)).ok()?;
+
+    state
+        .thumbnail_cache
+        .lock()
+        .await
+        .insert(surface_option.id, max_size, image_base64.clone());
+    Some(image_base64)
+}
+
+pub async fn select_surface_start(
+    handle: &AppHandle,
+    thumbnails: Thumbnails,
+) -> Result<Vec<Surface>, SelectionError> {
+    let (token, surfaces_options) = select_surface_options(handle).await?;
+
+    if let Thumbnails::None = thumbnails {
+        return Ok(surfaces_options
+            .iter()
+            .map(|surface_option| surface_option.surface(None))
+            .collect());
+    }
+
+    let surfaces = join_all(surfaces_options.iter().map(|surface_option| async move {
+        // a surface whose screenshot failed is still listed, without a thumbnail
+        let thumbnail = surface_thumbnail(handle, token, surface_option, thumbnails).await;
+        surface_option.surface(thumbnail)
+    }))
     .await;
 
-    return Ok(thumbnails);
+    return Ok(surfaces);
+}
+
+impl SurfaceOption {
+    pub fn id(&self) -> isize {
+        self.id
+    }
+
+    pub fn surface(&self, thumbnail: Option<String>) -> Surface {
+        Surface {
+            id: self.id,
+            title: self.title.clone(),
+            program: self.program.clone(),
+            surface_type: self.surface_type,
+            thumbnail,
+            display_identity: self.display_identity.clone(),
+        }
+    }
 }
@@ -0,0 +1,18 @@
+use cpal::traits::DeviceTrait;
+use tauri::AppHandle;
+
+use super::{
+    cmd_select_audio_close::select_audio_close, cmd_select_audio_start::select_audio_start,
+    selection_error::SelectionError,
+};
+
+/// Selects an input device returned by `list_audio_devices` by its name
+#[tauri::command]
+pub async fn set_audio_device(handle: AppHandle, name: String) -> Result<String, SelectionError> {
+    let device = select_audio_start()?
+        .into_iter()
+        .find(|device| device.name().map_or(false, |device_name| device_name == name))
+        .ok_or(SelectionError::AudioDeviceNotFound(name))?;
+
+    select_audio_close(&handle, Some(device)).await
+}
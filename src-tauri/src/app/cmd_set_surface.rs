@@ -0,0 +1,25 @@
+use tauri::AppHandle;
+
+use super::{
+    cmd_select_surface_close::{select_surface_close, SurfaceSelection},
+    cmd_select_surface_start::SurfaceType,
+    selection_error::SelectionError,
+};
+
+/// Selects a surface returned by `list_surfaces` and returns its title
+#[tauri::command]
+pub async fn set_surface(
+    handle: AppHandle,
+    id: isize,
+    surface_type: SurfaceType,
+) -> Result<String, SelectionError> {
+    select_surface_close(
+        &handle,
+        Some(SurfaceSelection {
+            id,
+            surface_type,
+            display_identity: None,
+        }),
+    )
+    .await
+}
@@ -2,9 +2,9 @@ use std::sync::atomic::Ordering;
 
 use tauri::{AppHandle, Manager};
 
-use crate::utils::notify_recording_status;
+use crate::{audio::audio_controller::AudioCommand, utils::notify_recording_status};
 
-use super::app_state::AppState;
+use super::{app_state::AppState, recording_session::RecordingSession, session_journal::SessionJournal};
 
 #[tauri::command]
 pub async fn start_input(handle: AppHandle) -> Result<(), String> {
@@ -22,14 +22,35 @@ pub async fn start_input(handle: AppHandle) -> Result<(), String> {
         return Err("Failed to notify recording status".to_string());
     }
 
+    // a session that was not finished nor cancelled keeps its files for another attempt
+    let session = RecordingSession::create(&handle)?;
+    let recording_start_time = state.recording_clock.now();
+    let auth = state.auth.lock().await.clone().ok_or("No auth")?;
+    // without an audio device the recording only has its steps
+    let steps_only = state.audio_device.lock().await.is_none();
+    let journal = SessionJournal::new(auth.space_name, recording_start_time, steps_only);
+    journal.save(&session).await?;
+    state.recording_session.lock().await.replace(session);
+    state.session_journal.lock().await.replace(journal);
+
+    state.steps_only.store(steps_only, Ordering::Relaxed);
     state.can_run_input.store(true, Ordering::Relaxed);
-    state.recording_start_time.lock().await.replace(std::time::SystemTime::now());
+    state.recording_start_time.lock().await.replace(recording_start_time);
+    state.keystroke_times.lock().await.clear();
+    state.step_times.lock().await.clear();
+    state.recording_pauses.lock().await.clear();
+    state.audio_capture_report.lock().await.take();
+    // finish_recording waits for the audio task to be done with the recording
+    state.audio_pending.send_replace(true);
     state
-        .can_run_audio
+        .audio_commands
         .lock()
         .await
-        .send(true)
+        .send(AudioCommand::Start)
         .await
-        .map_err(|_| "Failed to send to audio")?;
+        .map_err(|_| {
+            state.audio_pending.send_replace(false);
+            "Failed to send to audio"
+        })?;
     Ok(())
 }
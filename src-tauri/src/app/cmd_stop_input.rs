@@ -2,9 +2,9 @@ use std::sync::atomic::Ordering;
 
 use tauri::{AppHandle, Manager};
 
-use crate::utils::notify_recording_status;
+use crate::{audio::audio_controller::AudioCommand, utils::notify_recording_status};
 
-use super::app_state::AppState;
+use super::{app_state::AppState, session_journal::update_journal};
 
 #[tauri::command]
 pub async fn stop_input(handle: AppHandle) -> Result<(), String> {
@@ -23,12 +23,28 @@ pub async fn stop_input(handle: AppHandle) -> Result<(), String> {
     }
 
     state.can_run_input.store(false, Ordering::Relaxed);
-    state.recording_end_time.lock().await.replace(std::time::SystemTime::now());
+    state.surface_lost.store(false, Ordering::Relaxed);
+    state.paused_by_surface_loss.store(false, Ordering::Relaxed);
+    let recording_end_time = state.recording_clock.now();
+    state.recording_end_time.lock().await.replace(recording_end_time);
+
+    // a recording stopped while paused ends its pause
+    if state.recording_paused.swap(false, Ordering::Relaxed) {
+        if let Some(pause) = state.recording_pauses.lock().await.last_mut() {
+            pause.end.get_or_insert(recording_end_time);
+        }
+    }
+    let recording_pauses = state.recording_pauses.lock().await.clone();
+    update_journal(&handle, |journal| {
+        journal.recording_end_time = Some(recording_end_time);
+        journal.recording_pauses = recording_pauses;
+    })
+    .await;
     state
-        .can_run_audio
+        .audio_commands
         .lock()
         .await
-        .send(false)
+        .send(AudioCommand::Stop)
         .await
         .map_err(|_| "Failed to send to audio")?;
     Ok(())
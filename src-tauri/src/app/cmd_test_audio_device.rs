@@ -0,0 +1,89 @@
+use std::{sync::atomic::Ordering, time::Duration};
+
+use cpal::traits::DeviceTrait;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::audio::{
+    device_test::{input_statistics, InputStatistics},
+    input_config::input_stream_config,
+};
+
+use super::{
+    app_state::AppState, audio_sample::AudioSample, cmd_select_audio_start::select_audio_start,
+    selection_error::SelectionError,
+};
+
+const DEFAULT_TEST_SECONDS: u64 = 3;
+const MAX_TEST_SECONDS: u64 = 10;
+
+#[derive(Clone, Serialize)]
+pub struct AudioDeviceTest {
+    pub statistics: InputStatistics,
+    // the sample as it would be uploaded, as a wav data url
+    pub preview: Option<String>,
+}
+
+/// Records a few seconds from a device (the selected one by default) and measures what it picks up,
+/// optionally with the sample processed by the saved audio settings
+#[tauri::command]
+pub async fn test_audio_device(
+    handle: AppHandle,
+    name: Option<String>,
+    seconds: Option<u64>,
+    preview: Option<bool>,
+) -> Result<AudioDeviceTest, String> {
+    let state = handle
+        .try_state::<AppState>()
+        .ok_or("Failed to get AppState")?;
+    if state.can_run_input.load(Ordering::Relaxed) {
+        return Err("Cannot test an audio device while recording".to_string());
+    }
+
+    let audio_device = match name {
+        Some(name) => select_audio_start()?
+            .into_iter()
+            .find(|device| device.name().is_ok_and(|device_name| device_name == name))
+            .ok_or(SelectionError::AudioDeviceNotFound(name))?,
+        None => state
+            .audio_device
+            .lock()
+            .await
+            .clone()
+            .ok_or("No audio device")?,
+    };
+
+    // tested with the config it records with
+    let audio_settings = state.settings.lock().await.audio.clone();
+    let device_name = audio_device.name().map_err(|e| e.to_string())?;
+    let config = input_stream_config(
+        &audio_device,
+        audio_settings.input_configs.get(&device_name),
+    )
+    .map_err(|e| e.to_string())?;
+
+    let duration = Duration::from_secs(
+        seconds
+            .unwrap_or(DEFAULT_TEST_SECONDS)
+            .clamp(1, MAX_TEST_SECONDS),
+    );
+    let mut sample =
+        AudioSample::record(&handle, audio_device, config, duration, "device_test").await?;
+    let statistics =
+        input_statistics(&sample.filepath, duration, &sample.report).map_err(|e| e.to_string())?;
+
+    // converted exactly as a recording would be
+    let preview = match preview.unwrap_or(false) {
+        true => Some(
+            sample
+                .converted("device_test_converted", &audio_settings)
+                .await?,
+        ),
+        false => None,
+    };
+
+    Ok(AudioDeviceTest {
+        statistics,
+        preview,
+    })
+}
@@ -1,15 +1,34 @@
 pub mod app_state;
+pub mod app_settings;
+pub mod audio_sample;
+pub mod recording_session;
+pub mod session_journal;
+pub mod selection_error;
 
 pub mod cmd_start_input;
 pub mod cmd_stop_input;
+pub mod cmd_pause_recording;
+pub mod cmd_resume_recording;
 
 pub mod cmd_select_surface;
 pub mod cmd_select_surface_close;
 pub mod cmd_select_surface_start;
+pub mod cmd_list_surfaces;
+pub mod cmd_set_surface;
 
 pub mod cmd_select_audio;
 pub mod cmd_select_audio_close;
 pub mod cmd_select_audio_start;
+pub mod cmd_list_audio_devices;
+pub mod cmd_set_audio_device;
+pub mod cmd_list_loopback_devices;
+
+pub mod cmd_restored_selection;
+
+pub mod cmd_audio_settings;
+pub mod cmd_audio_configs;
+pub mod cmd_preview_audio_processing;
+pub mod cmd_test_audio_device;
 
 pub mod cmd_set_auth;
 pub mod cmd_is_auth;
@@ -18,3 +37,4 @@ pub mod cmd_get_locale;
 
 pub mod cmd_finish_recording;
 pub mod cmd_cancel_recording;
+pub mod cmd_interrupted_recordings;
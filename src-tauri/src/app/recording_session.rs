@@ -0,0 +1,107 @@
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use tauri::{AppHandle, Manager};
+
+const SESSIONS_DIR: &str = "sessions";
+const METADATA_FILE: &str = "metadata.json";
+
+// sessions created by this process, so that two created within the same millisecond differ
+static CREATED_SESSIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// The files of one recording, kept in a directory of their own until it is finished or cancelled,
+/// so that a failed upload is not overwritten by the next recording
+#[derive(Clone, Debug)]
+pub struct RecordingSession {
+    pub id: String,
+    pub dir: PathBuf,
+}
+
+impl RecordingSession {
+    pub fn sessions_dir(handle: &AppHandle) -> Result<PathBuf, String> {
+        let cache_dir = handle.path().app_cache_dir().map_err(|e| e.to_string())?;
+        Ok(cache_dir.join(SESSIONS_DIR))
+    }
+
+    pub fn create(handle: &AppHandle) -> Result<RecordingSession, String> {
+        let start_millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| e.to_string())?
+            .as_millis();
+        // the process id tells apart the sessions of two instances of the app
+        let id = format!(
+            "{}-{}-{}",
+            start_millis,
+            std::process::id(),
+            CREATED_SESSIONS.fetch_add(1, Ordering::Relaxed)
+        );
+        let session = RecordingSession {
+            dir: Self::sessions_dir(handle)?.join(&id),
+            id,
+        };
+
+        for dir in [session.audio_dir(), session.steps_dir()] {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        Ok(session)
+    }
+
+    /// The session of an earlier recording, named by its id
+    pub fn open(handle: &AppHandle, id: &str) -> Result<RecordingSession, String> {
+        // an id cannot lead out of the sessions directory
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_digit() || c == '-') {
+            return Err(format!("Invalid recording session: {}", id));
+        }
+        let dir = Self::sessions_dir(handle)?.join(id);
+        if !dir.is_dir() {
+            return Err(format!("No recording session {}", id));
+        }
+        Ok(RecordingSession {
+            id: id.to_string(),
+            dir,
+        })
+    }
+
+    /// The sessions left on disk, oldest first
+    pub fn list(handle: &AppHandle) -> Result<Vec<RecordingSession>, String> {
+        let entries = match std::fs::read_dir(Self::sessions_dir(handle)?) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.to_string()),
+        };
+        let mut sessions: Vec<RecordingSession> = entries
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter_map(|id| Self::open(handle, &id).ok())
+            .collect();
+        sessions.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(sessions)
+    }
+
+    pub fn audio_dir(&self) -> PathBuf {
+        self.dir.join("audio")
+    }
+
+    pub fn steps_dir(&self) -> PathBuf {
+        self.dir.join("steps")
+    }
+
+    pub fn metadata_path(&self) -> PathBuf {
+        self.dir.join(METADATA_FILE)
+    }
+
+    /// The index of the next step, from the payloads already written
+    pub fn next_step(&self) -> Result<usize, String> {
+        let steps = std::fs::read_dir(self.steps_dir()).map_err(|e| e.to_string())?;
+        Ok(steps.count())
+    }
+
+    pub fn remove(&self) -> Result<(), String> {
+        match std::fs::remove_dir_all(&self.dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use std::fmt;
+
+use serde::Serialize;
+
+/// Errors returned by the surface and audio device selection commands,
+/// serialized as `{ "kind": ..., "message": ... }` for the frontend
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "message")]
+pub enum SelectionError {
+    AppState,
+    CaptureAccessDenied,
+    CapturableContent,
+    SurfaceNotFound(isize),
+    AudioHost(String),
+    AudioDeviceNotFound(String),
+}
+
+impl fmt::Display for SelectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelectionError::AppState => write!(f, "Failed to get AppState"),
+            SelectionError::CaptureAccessDenied => write!(f, "Capture access was denied"),
+            SelectionError::CapturableContent => write!(f, "Failed to get capturable content"),
+            SelectionError::SurfaceNotFound(id) => write!(f, "Surface {} not found", id),
+            SelectionError::AudioHost(e) => write!(f, "Audio host error: {}", e),
+            SelectionError::AudioDeviceNotFound(name) => write!(f, "Audio device '{}' not found", name),
+        }
+    }
+}
+
+impl std::error::Error for SelectionError {}
+
+impl From<SelectionError> for String {
+    fn from(error: SelectionError) -> String {
+        error.to_string()
+    }
+}
@@ -0,0 +1,108 @@
+use std::{collections::BTreeSet, time::SystemTime};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::audio::{
+    capture::CaptureReport,
+    segments::{AudioSegment, SegmentContext},
+};
+
+use super::{
+    app_state::{AppState, RecordingPause, StepTime},
+    recording_session::RecordingSession,
+};
+
+const JOURNAL_FILE: &str = "journal.json";
+
+/// What a recording needs to be finished, written next to its files as it goes,
+/// so that it can be finished or cancelled after the app was interrupted
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SessionJournal {
+    // the access token is not written, the recording is recovered with the one of the space signed in
+    pub space_name: String,
+    pub recording_start_time: SystemTime,
+    pub recording_end_time: Option<SystemTime>,
+    // recorded without an audio device, for the recovery to finish it the same way.
+    // the journals written before this all had one
+    #[serde(default)]
+    pub steps_only: bool,
+    pub recording_pauses: Vec<RecordingPause>,
+    // written with each change of the journal, the latest ones can be missing
+    pub keystroke_times: Vec<SystemTime>,
+    pub step_times: Vec<StepTime>,
+    pub audio_context: Option<SegmentContext>,
+    pub audio_segments: Vec<AudioSegment>,
+    pub audio_capture_report: Option<CaptureReport>,
+    pub uploaded_steps: BTreeSet<usize>,
+}
+
+impl SessionJournal {
+    pub fn new(
+        space_name: String,
+        recording_start_time: SystemTime,
+        steps_only: bool,
+    ) -> SessionJournal {
+        SessionJournal {
+            space_name,
+            recording_start_time,
+            recording_end_time: None,
+            steps_only,
+            recording_pauses: Vec::new(),
+            keystroke_times: Vec::new(),
+            step_times: Vec::new(),
+            audio_context: None,
+            audio_segments: Vec::new(),
+            audio_capture_report: None,
+            uploaded_steps: BTreeSet::new(),
+        }
+    }
+
+    pub fn load(session: &RecordingSession) -> Result<SessionJournal, String> {
+        let contents =
+            std::fs::read_to_string(session.dir.join(JOURNAL_FILE)).map_err(|e| e.to_string())?;
+        serde_json::from_str(&contents).map_err(|e| e.to_string())
+    }
+
+    /// Replaces the journal of the session, so that a crash while writing leaves the previous one
+    pub async fn save(&self, session: &RecordingSession) -> Result<(), String> {
+        let contents = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        let filepath = session.dir.join(JOURNAL_FILE);
+        let tmp_filepath = filepath.with_extension("json.tmp");
+        tokio::fs::write(&tmp_filepath, contents)
+            .await
+            .map_err(|e| e.to_string())?;
+        tokio::fs::rename(tmp_filepath, filepath)
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Applies a change to the journal of the current recording and writes it, returns whether it was written.
+/// The recording goes on if it cannot be written, it would only be lost to a crash.
+pub async fn update_journal(
+    app_handle: &AppHandle,
+    update: impl FnOnce(&mut SessionJournal),
+) -> bool {
+    let Some(state) = app_handle.try_state::<AppState>() else {
+        return false;
+    };
+    let Some(session) = state.recording_session.lock().await.clone() else {
+        return false;
+    };
+    let keystroke_times = state.keystroke_times.lock().await.clone();
+
+    let mut journal = state.session_journal.lock().await;
+    let Some(journal) = journal.as_mut() else {
+        return false;
+    };
+    update(journal);
+    journal.keystroke_times = keystroke_times;
+    match journal.save(&session).await {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("Failed to write the session journal: {}", e);
+            false
+        }
+    }
+}
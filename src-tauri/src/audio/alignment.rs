@@ -0,0 +1,138 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// Monotonic clock shared by the input events and the audio capture. Its times read as system times,
+/// but they do not jump when the wall clock is set.
+#[derive(Clone, Copy, Debug)]
+pub struct RecordingClock {
+    origin: Instant,
+    origin_time: SystemTime,
+}
+
+impl Default for RecordingClock {
+    fn default() -> RecordingClock {
+        RecordingClock {
+            origin: Instant::now(),
+            origin_time: SystemTime::now(),
+        }
+    }
+}
+
+impl RecordingClock {
+    pub fn time(&self, instant: Instant) -> SystemTime {
+        match instant.checked_duration_since(self.origin) {
+            Some(elapsed) => self.origin_time + elapsed,
+            None => self.origin_time - self.origin.duration_since(instant),
+        }
+    }
+
+    pub fn now(&self) -> SystemTime {
+        self.time(Instant::now())
+    }
+}
+
+/// A frame of the capture file and the time its first sample was captured, on the recording clock
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClockAnchor {
+    pub frame: u64,
+    // microseconds since the epoch
+    pub time_us: u64,
+}
+
+impl ClockAnchor {
+    pub fn new(frame: u64, time: SystemTime) -> ClockAnchor {
+        ClockAnchor {
+            frame,
+            time_us: epoch_us(time),
+        }
+    }
+}
+
+fn epoch_us(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_micros() as u64
+}
+
+/// Maps the times of the recording clock to frames of the capture, from the capture times the device gave
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioAlignment {
+    pub sample_rate: u32,
+    pub anchors: Vec<ClockAnchor>,
+}
+
+impl AudioAlignment {
+    /// The frame captured at a time, counted from the last anchor before it at the nominal rate,
+    /// which drifts by no more than the device clock does between two anchors.
+    /// A time during a pause or a gap maps to the first frame captured after it.
+    pub fn frame_at(&self, time: SystemTime) -> Option<u64> {
+        let time_us = epoch_us(time);
+        let next = self
+            .anchors
+            .iter()
+            .position(|anchor| anchor.time_us > time_us)
+            .unwrap_or(self.anchors.len());
+        let Some(anchor) = self.anchors[..next].last() else {
+            // before the capture started
+            return self.anchors.first().map(|anchor| anchor.frame);
+        };
+
+        let elapsed_frames = (time_us - anchor.time_us) as f64 * self.sample_rate as f64 / 1e6;
+        let frame = anchor.frame + elapsed_frames.round() as u64;
+        Some(match self.anchors.get(next) {
+            Some(next_anchor) => frame.min(next_anchor.frame),
+            None => frame,
+        })
+    }
+
+    /// Position of a moment in the captured audio
+    pub fn offset(&self, time: SystemTime) -> Option<Duration> {
+        if self.sample_rate == 0 {
+            return None;
+        }
+        let frame = self.frame_at(time)?;
+        Some(Duration::from_secs_f64(
+            frame as f64 / self.sample_rate as f64,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_follows_the_monotonic_time() {
+        let clock = RecordingClock::default();
+        let later = clock.origin + Duration::from_millis(1500);
+        assert_eq!(
+            clock.time(later).duration_since(clock.origin_time).unwrap(),
+            Duration::from_millis(1500)
+        );
+    }
+
+    #[test]
+    fn frames_are_counted_from_the_last_anchor() {
+        let start = UNIX_EPOCH + Duration::from_secs(1000);
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        // one second captured, a pause of two seconds, then another second
+        let alignment = AudioAlignment {
+            sample_rate: 1000,
+            anchors: vec![ClockAnchor::new(0, at(0)), ClockAnchor::new(1000, at(3000))],
+        };
+
+        assert_eq!(alignment.frame_at(at(250)), Some(250));
+        assert_eq!(alignment.frame_at(at(3500)), Some(1500));
+        // during the pause, the frame captured when it ended
+        assert_eq!(alignment.frame_at(at(2000)), Some(1000));
+        assert_eq!(alignment.frame_at(start - Duration::from_secs(1)), Some(0));
+        assert_eq!(
+            alignment.offset(at(3500)),
+            Some(Duration::from_millis(1500))
+        );
+        assert_eq!(AudioAlignment::default().frame_at(at(0)), None);
+    }
+}
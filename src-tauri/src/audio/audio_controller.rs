@@ -1,113 +1,240 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
 use cpal::traits::{DeviceTrait, StreamTrait};
-use std::sync::{Arc, Mutex};
 use tauri::{AppHandle, Manager};
-use tokio::sync::mpsc::Receiver;
-
-use crate::{app::app_state::AppState, utils};
+use tokio::sync::mpsc::{self, Receiver};
+
+use crate::app::{app_state::AppState, session_journal::update_journal};
+
+use super::{
+    audio_settings::{AudioFormat, SystemAudioSettings},
+    capture::{
+        build_capture_stream, log_stream_error, start_capture, CaptureOutput, CaptureReport,
+        CaptureWriter, CapturedSegment,
+    },
+    input_config::input_stream_config,
+    input_recovery::RecoverableInput,
+    level_meter::LevelMeter,
+    loopback::{find_loopback_device, loopback_config, open_loopback},
+    segments::{remove_captures, upload_segments, SegmentContext, SEGMENT_SECONDS},
+};
+
+/// What the recording commands ask of the audio controller
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AudioCommand {
+    Start,
+    Pause,
+    Resume,
+    Stop,
+}
 
-use super::convert_audio::convert_audio;
+/// Starts capturing what the system plays, next to the microphone
+fn start_system_capture(
+    app_handle: &AppHandle,
+    output: CaptureOutput,
+    settings: &SystemAudioSettings,
+) -> Result<(cpal::Stream, CaptureWriter), Box<dyn std::error::Error + Send + Sync>> {
+    let device = find_loopback_device(settings.device.as_deref())?;
+    let config = loopback_config(&device)?;
+
+    let (input, capture_writer) = start_capture(
+        app_handle,
+        output,
+        config.channels(),
+        config.sample_rate().0,
+        None,
+    )?;
+    let stream = open_loopback(&device, || {
+        build_capture_stream(&device, config, input, log_stream_error)
+    })?;
+    stream.play()?;
+    Ok((stream, capture_writer))
+}
 
 pub async fn audio_controller(
     app_handle: &AppHandle,
-    mut async_receiver: Receiver<bool>,
+    mut async_receiver: Receiver<AudioCommand>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let tmp_path = app_handle.path().app_cache_dir()?;
-    let filepath = tmp_path.join("output.wav");
 
-    // if file exists, delete it
-    if filepath.exists() {
-        std::fs::remove_file(&filepath)?;
+    // recordings made before sessions left their segments next to each other, in any of the upload formats
+    for entry in std::fs::read_dir(&tmp_path)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let is_audio = [AudioFormat::Wav, AudioFormat::Flac, AudioFormat::Opus]
+            .iter()
+            .any(|format| name.ends_with(format.extension()));
+        if is_audio && (name.starts_with("output.") || name.starts_with("system.")) {
+            if let Err(e) = std::fs::remove_file(&path) {
+                eprintln!("Failed to remove {}: {}", path.display(), e);
+            }
+        }
     }
 
     loop {
-        while let Some(can_run) = async_receiver.recv().await {
-            if can_run {
+        while let Some(command) = async_receiver.recv().await {
+            if command == AudioCommand::Start {
                 break;
             }
         }
 
-        let state = app_handle
-            .try_state::<AppState>()
-            .ok_or("Failed to get AppState")?;
-
-        let audio_device_lock = state.audio_device.lock().await;
-        let audio_device = audio_device_lock.as_ref().ok_or("No audio device")?;
-
-        let config = audio_device
-            .default_input_config()
-            .expect("Failed to get default input config");
-
-        let base_sample_format = match config.sample_format() {
-            cpal::SampleFormat::I16 => Ok::<cpal::SampleFormat, String>(cpal::SampleFormat::I16),
-            cpal::SampleFormat::I32 => Ok::<cpal::SampleFormat, String>(cpal::SampleFormat::I32),
-            cpal::SampleFormat::F32 => Ok::<cpal::SampleFormat, String>(cpal::SampleFormat::F32),
-            _ => Err("Unsupported sample format".into()),
-        }?;
-
-        let (bits_per_sample, sample_format) = match config.sample_format() {
-            cpal::SampleFormat::I16 => Ok::<_, String>((16, hound::SampleFormat::Int)),
-            cpal::SampleFormat::I32 => Ok::<_, String>((32, hound::SampleFormat::Int)),
-            cpal::SampleFormat::F32 => Ok::<_, String>((32, hound::SampleFormat::Float)),
-            _ => return Err("Unsupported sample format".into()),
-        }?;
-
-        let spec = hound::WavSpec {
-            channels: config.channels(),
-            sample_rate: config.sample_rate().0,
-            bits_per_sample,
-            sample_format,
-        };
+        // the commands of a recording whose audio failed are drained above, the next one tries again
+        if let Err(e) = record_audio(app_handle, &mut async_receiver).await {
+            eprintln!("Failed to record audio: {}", e);
+        }
+        if let Some(state) = app_handle.try_state::<AppState>() {
+            state.audio_pending.send_replace(false);
+        }
+    }
+}
 
-        let buffer = hound::WavWriter::create(filepath.clone(), spec)?;
-        let writer = Arc::new(Mutex::new(Some(buffer)));
-        let writer_clone = writer.clone();
-        let err_fn = move |err| {
-            eprintln!("an error occurred on stream: {}", err);
+/// Captures, converts and uploads the audio of one recording, until it is stopped
+async fn record_audio(
+    app_handle: &AppHandle,
+    async_receiver: &mut Receiver<AudioCommand>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let state = app_handle
+        .try_state::<AppState>()
+        .ok_or("Failed to get AppState")?;
+    state.audio_segments.lock().await.clear();
+    state.failed_audio_segments.lock().await.clear();
+
+    let session = state
+        .recording_session
+        .lock()
+        .await
+        .clone()
+        .ok_or("No recording session")?;
+    let filepath = session.audio_dir().join("output.wav");
+    let system_filepath = session.audio_dir().join("system.wav");
+
+    // a recording started without a device has nothing to capture, the next one may have
+    if state.steps_only.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    // the device is not kept locked, it can be selected again while the audio is uploaded
+    let Some(audio_device) = state.audio_device.lock().await.clone() else {
+        return Ok(());
+    };
+
+    let audio_settings = state.settings.lock().await.audio.clone();
+    let config = input_stream_config(
+        &audio_device,
+        audio_settings.input_configs.get(&audio_device.name()?),
+    )?;
+    let level_meter = LevelMeter::new(
+        config.sample_rate().0,
+        config.channels(),
+        &audio_settings.level_monitor,
+    );
+
+    // the streams cannot be sent across threads, they only live in this block which never awaits
+    let (uploads, capture_report) = {
+        // the microphone decides when a segment ends, the system audio follows it
+        let sequence = Arc::new(AtomicUsize::new(0));
+        let (segment_sender, segments) = mpsc::unbounded_channel::<CapturedSegment>();
+        let (input, capture_writer) = start_capture(
+            app_handle,
+            CaptureOutput::Segments {
+                filepath,
+                sequence: sequence.clone(),
+                segment_frames: Some(SEGMENT_SECONDS * config.sample_rate().0 as u64),
+                sender: segment_sender,
+            },
+            config.channels(),
+            config.sample_rate().0,
+            Some(level_meter),
+        )?;
+        // a lost microphone is replaced without stopping the recording
+        let mut microphone = RecoverableInput::start(&audio_device, config, input)?;
+        let audio_start_time = state.recording_clock.now();
+
+        // the recording goes on with the microphone only if the system audio cannot be captured
+        let (system_segment_sender, system_segments) = mpsc::unbounded_channel::<CapturedSegment>();
+        let system_output = CaptureOutput::Segments {
+            filepath: system_filepath,
+            sequence: sequence.clone(),
+            segment_frames: None,
+            sender: system_segment_sender,
         };
-
-        let stream = match base_sample_format {
-            cpal::SampleFormat::I16 => audio_device
-                .build_input_stream(
-                    &config.into(),
-                    move |data, _: &_| utils::write_audio_data::<i16, i16>(data, &writer_clone),
-                    err_fn,
-                    None,
-                )?,
-            cpal::SampleFormat::I32 => audio_device
-                .build_input_stream(
-                    &config.into(),
-                    move |data, _: &_| utils::write_audio_data::<i32, i32>(data, &writer_clone),
-                    err_fn,
-                    None,
-                )?,
-            cpal::SampleFormat::F32 => audio_device
-                .build_input_stream(
-                    &config.into(),
-                    move |data, _: &_| utils::write_audio_data::<f32, f32>(data, &writer_clone),
-                    err_fn,
-                    None,
-                )?,
-            sample_format => {
-                return Err(format!("Unsupported sample format '{:?}'", sample_format).into())
+        let system_capture = if audio_settings.system_audio.enabled {
+            match start_system_capture(app_handle, system_output, &audio_settings.system_audio) {
+                Ok(system_capture) => Some((system_capture, state.recording_clock.now())),
+                Err(e) => {
+                    eprintln!("Failed to capture the system audio: {}", e);
+                    None
+                }
             }
+        } else {
+            None
         };
 
-        stream.play()?;
-        while let Some(can_run) = futures::executor::block_on(async_receiver.recv()) {
-            if !can_run {
-                break;
+        let context = SegmentContext {
+            settings: audio_settings,
+            audio_start_time,
+            system_offset: system_capture
+                .as_ref()
+                .and_then(|(_, system_start_time)| {
+                    system_start_time.duration_since(audio_start_time).ok()
+                })
+                .unwrap_or_default(),
+        };
+        let uploads = tauri::async_runtime::spawn(upload_segments(
+            app_handle.clone(),
+            context,
+            segments,
+            system_capture.as_ref().map(|_| system_segments),
+        ));
+
+        // a pause keeps the files open, what the devices deliver meanwhile is left out
+        loop {
+            let paused = match microphone.watch(app_handle, &capture_writer, async_receiver) {
+                AudioCommand::Pause => true,
+                AudioCommand::Resume => false,
+                _ => break,
+            };
+            microphone.set_paused(&capture_writer, paused);
+            if let Some(((_, system_writer), _)) = &system_capture {
+                system_writer.set_paused(paused);
             }
         }
-        drop(stream);
-
-
-        writer
-            .lock()
-            .unwrap()
-            .take()
-            .unwrap()
-            .finalize()?;
-
-        convert_audio(&filepath, &spec)?;
+        // dropping the stream drops the input, the writer then drains what is left
+        drop(microphone);
+        let system_writer = system_capture.map(|((system_stream, system_writer), _)| {
+            drop(system_stream);
+            system_writer
+        });
+
+        // the writers send their last segment as they finish
+        let capture_report = capture_writer.finish().unwrap_or_else(|e| {
+            eprintln!("Failed to finish the audio capture: {}", e);
+            CaptureReport::default()
+        });
+        if let Some(system_writer) = system_writer {
+            if let Err(e) = system_writer.finish() {
+                eprintln!("Failed to finish the system audio capture: {}", e);
+            }
+        }
+        (uploads, capture_report)
+    };
+
+    *state.audio_capture_report.lock().await = Some(capture_report.clone());
+
+    // the segments that failed to upload or to convert are left to finish_recording
+    let (audio_segments, failed_sequences) = uploads.await??;
+    *state.audio_segments.lock().await = audio_segments.clone();
+    *state.failed_audio_segments.lock().await = failed_sequences;
+    let journaled_segments = audio_segments.clone();
+    if update_journal(app_handle, |journal| {
+        journal.audio_segments = journaled_segments;
+        journal.audio_capture_report = Some(capture_report);
+    })
+    .await
+    {
+        audio_segments.iter().for_each(remove_captures);
     }
+    Ok(())
 }
@@ -0,0 +1,348 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Settings of the audio processing, stored with the rest of the app settings
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub loudness: LoudnessSettings,
+    pub silence: SilenceSettings,
+    pub dsp: DspSettings,
+    pub keyboard_ducking: KeyboardDuckingSettings,
+    pub system_audio: SystemAudioSettings,
+    pub encoding: EncodingSettings,
+    pub level_monitor: LevelMonitorSettings,
+    pub step_cues: StepCueSettings,
+    // by device name, the other devices are opened with their default config
+    pub input_configs: BTreeMap<String, InputConfig>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoudnessSettings {
+    pub target_lufs: f64,
+    pub true_peak_ceiling_dbtp: f64,
+    // keeps the noise of near-silent recordings from being amplified too much
+    pub max_gain_db: f64,
+}
+
+impl Default for LoudnessSettings {
+    fn default() -> LoudnessSettings {
+        LoudnessSettings {
+            target_lufs: -16.0,
+            true_peak_ceiling_dbtp: -1.0,
+            max_gain_db: 30.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SilenceSettings {
+    // level above the noise floor from which a frame is considered speech
+    pub vad_threshold_db: f64,
+    pub trim_silence: bool,
+    pub shorten_pauses: bool,
+    pub max_pause_ms: u64,
+    // silence kept before and after speech when cutting
+    pub padding_ms: u64,
+}
+
+impl Default for SilenceSettings {
+    fn default() -> SilenceSettings {
+        SilenceSettings {
+            vad_threshold_db: 10.0,
+            trim_silence: false,
+            shorten_pauses: false,
+            max_pause_ms: 1500,
+            padding_ms: 300,
+        }
+    }
+}
+
+/// Clean-up stages, all disabled by default
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DspSettings {
+    pub high_pass: HighPassSettings,
+    pub noise_suppression: NoiseSuppressionSettings,
+    pub noise_gate: NoiseGateSettings,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HighPassSettings {
+    pub enabled: bool,
+    pub cutoff_hz: f64,
+}
+
+impl Default for HighPassSettings {
+    fn default() -> HighPassSettings {
+        HighPassSettings {
+            enabled: false,
+            cutoff_hz: 80.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoiseSuppressionSettings {
+    pub enabled: bool,
+    // how much the noise can be attenuated at most
+    pub reduction_db: f64,
+}
+
+impl Default for NoiseSuppressionSettings {
+    fn default() -> NoiseSuppressionSettings {
+        NoiseSuppressionSettings {
+            enabled: false,
+            reduction_db: 12.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoiseGateSettings {
+    pub enabled: bool,
+    pub threshold_db: f64,
+    pub attenuation_db: f64,
+    pub release_ms: u64,
+}
+
+impl Default for NoiseGateSettings {
+    fn default() -> NoiseGateSettings {
+        NoiseGateSettings {
+            enabled: false,
+            threshold_db: -45.0,
+            attenuation_db: 20.0,
+            release_ms: 150,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyboardDuckingSettings {
+    pub enabled: bool,
+    pub attenuation_db: f64,
+    // window around each key event, the sound of a key comes a little after the event
+    pub before_ms: u64,
+    pub after_ms: u64,
+}
+
+impl Default for KeyboardDuckingSettings {
+    fn default() -> KeyboardDuckingSettings {
+        // the levels of the recording are already shaped by the loudness and limiter stages,
+        // ducking is left to the user to turn on
+        KeyboardDuckingSettings {
+            enabled: false,
+            attenuation_db: 20.0,
+            before_ms: 20,
+            after_ms: 80,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SystemAudioMode {
+    #[default]
+    Mix,
+    // uploaded as a second track, next to the narration
+    SeparateTrack,
+}
+
+/// What the system plays, captured through a loopback source
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SystemAudioSettings {
+    pub enabled: bool,
+    // name of the loopback device, the first one found when not set
+    pub device: Option<String>,
+    pub mode: SystemAudioMode,
+    // levels of both sources in the mix
+    pub microphone_level_db: f64,
+    pub system_level_db: f64,
+}
+
+impl Default for SystemAudioSettings {
+    fn default() -> SystemAudioSettings {
+        SystemAudioSettings {
+            enabled: false,
+            device: None,
+            mode: SystemAudioMode::Mix,
+            microphone_level_db: 0.0,
+            system_level_db: -6.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioFormat {
+    Wav,
+    #[default]
+    Flac,
+    // in an ogg container
+    Opus,
+}
+
+impl AudioFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "wav",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Opus => "opus",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "audio/wav",
+            AudioFormat::Flac => "audio/flac",
+            AudioFormat::Opus => "audio/ogg",
+        }
+    }
+}
+
+/// Format the audio is uploaded in
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EncodingSettings {
+    pub format: AudioFormat,
+    pub opus_bitrate_kbps: u32,
+}
+
+impl Default for EncodingSettings {
+    fn default() -> EncodingSettings {
+        EncodingSettings {
+            format: AudioFormat::Flac,
+            opus_bitrate_kbps: 32,
+        }
+    }
+}
+
+/// When to warn that the microphone picks nothing up, or too much
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LevelMonitorSettings {
+    pub silence_threshold_db: f64,
+    pub silence_warning_ms: u64,
+    pub clipping_warning_ms: u64,
+}
+
+impl Default for LevelMonitorSettings {
+    fn default() -> LevelMonitorSettings {
+        LevelMonitorSettings {
+            silence_threshold_db: -60.0,
+            silence_warning_ms: 10000,
+            clipping_warning_ms: 500,
+        }
+    }
+}
+
+/// How much of the audio around a step is given to it in the cue sheet
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StepCueSettings {
+    pub padding_ms: u64,
+}
+
+impl Default for StepCueSettings {
+    fn default() -> StepCueSettings {
+        StepCueSettings { padding_ms: 500 }
+    }
+}
+
+/// Sample rate and channel count a device is opened with, one of those it supports
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct InputConfig {
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+impl AudioSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        let loudness = &self.loudness;
+        if !(-40.0..=-5.0).contains(&loudness.target_lufs) {
+            return Err("Loudness target must be between -40 and -5 LUFS".to_string());
+        }
+        if !(-12.0..=0.0).contains(&loudness.true_peak_ceiling_dbtp) {
+            return Err("True peak ceiling must be between -12 and 0 dBTP".to_string());
+        }
+        if !(0.0..=60.0).contains(&loudness.max_gain_db) {
+            return Err("Maximum gain must be between 0 and 60 dB".to_string());
+        }
+
+        let silence = &self.silence;
+        if !(3.0..=30.0).contains(&silence.vad_threshold_db) {
+            return Err("Voice detection threshold must be between 3 and 30 dB".to_string());
+        }
+        if silence.max_pause_ms < 200 {
+            return Err("Pauses cannot be shortened under 200ms".to_string());
+        }
+        if silence.padding_ms > 2000 {
+            return Err("Padding around speech cannot exceed 2000ms".to_string());
+        }
+
+        let dsp = &self.dsp;
+        if !(20.0..=300.0).contains(&dsp.high_pass.cutoff_hz) {
+            return Err("High-pass cutoff must be between 20 and 300 Hz".to_string());
+        }
+        if !(0.0..=40.0).contains(&dsp.noise_suppression.reduction_db) {
+            return Err("Noise reduction must be between 0 and 40 dB".to_string());
+        }
+        if !(-80.0..=-10.0).contains(&dsp.noise_gate.threshold_db) {
+            return Err("Noise gate threshold must be between -80 and -10 dB".to_string());
+        }
+        if !(0.0..=80.0).contains(&dsp.noise_gate.attenuation_db) {
+            return Err("Noise gate attenuation must be between 0 and 80 dB".to_string());
+        }
+        if !(10..=2000).contains(&dsp.noise_gate.release_ms) {
+            return Err("Noise gate release must be between 10 and 2000ms".to_string());
+        }
+
+        let keyboard_ducking = &self.keyboard_ducking;
+        if !(0.0..=60.0).contains(&keyboard_ducking.attenuation_db) {
+            return Err("Keyboard ducking attenuation must be between 0 and 60 dB".to_string());
+        }
+        if keyboard_ducking.before_ms > 200 || keyboard_ducking.after_ms > 500 {
+            return Err("Keyboard ducking window is too long".to_string());
+        }
+
+        let system_audio = &self.system_audio;
+        if !(-40.0..=12.0).contains(&system_audio.microphone_level_db)
+            || !(-40.0..=12.0).contains(&system_audio.system_level_db)
+        {
+            return Err(
+                "Microphone and system audio levels must be between -40 and 12 dB".to_string(),
+            );
+        }
+
+        if !(6..=128).contains(&self.encoding.opus_bitrate_kbps) {
+            return Err("Opus bitrate must be between 6 and 128 kbps".to_string());
+        }
+
+        let level_monitor = &self.level_monitor;
+        if !(-100.0..=-20.0).contains(&level_monitor.silence_threshold_db) {
+            return Err("Silence threshold must be between -100 and -20 dB".to_string());
+        }
+        if level_monitor.silence_warning_ms < 1000 || level_monitor.clipping_warning_ms < 100 {
+            return Err("Level warnings need at least 1000ms of silence or 100ms of clipping".to_string());
+        }
+
+        if self.step_cues.padding_ms > 5000 {
+            return Err("Padding around steps cannot exceed 5000ms".to_string());
+        }
+
+        for config in self.input_configs.values() {
+            if !(8000..=384000).contains(&config.sample_rate) || !(1..=32).contains(&config.channels) {
+                return Err("Input configs need a sample rate between 8 and 384 kHz and up to 32 channels".to_string());
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use std::f64::consts::{FRAC_1_SQRT_2, PI};
+
+/// Second order IIR filter, direct form I
+#[derive(Clone)]
+pub struct Biquad {
+    b: [f64; 3],
+    a: [f64; 3],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    /// Coefficients normalized so that a[0] is 1
+    pub fn new(b: [f64; 3], a: [f64; 3]) -> Biquad {
+        Biquad {
+            b,
+            a,
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    /// Butterworth high-pass filter, from the Audio EQ Cookbook
+    pub fn high_pass(sample_rate: u32, cutoff: f64) -> Biquad {
+        let omega = 2.0 * PI * cutoff / sample_rate as f64;
+        let alpha = omega.sin() / (2.0 * FRAC_1_SQRT_2);
+        let cos = omega.cos();
+        let a0 = 1.0 + alpha;
+        Biquad::new(
+            [
+                (1.0 + cos) / 2.0 / a0,
+                -(1.0 + cos) / a0,
+                (1.0 + cos) / 2.0 / a0,
+            ],
+            [1.0, -2.0 * cos / a0, (1.0 - alpha) / a0],
+        )
+    }
+
+    pub fn process(&mut self, sample: f64) -> f64 {
+        let output = self.b[0] * sample + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[1] * self.y[0]
+            - self.a[2] * self.y[1];
+        self.x = [sample, self.x[0]];
+        self.y = [output, self.y[0]];
+        output
+    }
+}
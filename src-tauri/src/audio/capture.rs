@@ -0,0 +1,818 @@
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime},
+};
+
+use cpal::{
+    traits::{DeviceTrait, StreamTrait},
+    FromSample, Sample, SizedSample, SupportedStreamConfig,
+};
+use hound::{SampleFormat, WavSpec, WavWriter};
+use rtrb::{Consumer, Producer, RingBuffer};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::app::app_state::AppState;
+
+use super::{
+    alignment::{AudioAlignment, ClockAnchor, RecordingClock},
+    level_meter::LevelMeter,
+};
+
+// audio the ring buffer can hold while the writer thread is busy writing to disk
+const BUFFER_SECONDS: usize = 2;
+const WRITER_INTERVAL: Duration = Duration::from_millis(20);
+// how often the capture time of a frame is taken, and how many can wait for the writer
+const MARKER_SECONDS: u64 = 1;
+const MARKER_CAPACITY: usize = 64;
+
+#[derive(Default)]
+struct CaptureCounters {
+    captured_frames: AtomicU64,
+    dropped_frames: AtomicU64,
+    overruns: AtomicU64,
+    // the frames delivered during a pause are left out of the capture
+    paused: AtomicBool,
+}
+
+impl CaptureCounters {
+    fn report(&self) -> CaptureReport {
+        CaptureReport {
+            captured_frames: self.captured_frames.load(Ordering::Relaxed),
+            dropped_frames: self.dropped_frames.load(Ordering::Relaxed),
+            overruns: self.overruns.load(Ordering::Relaxed),
+            gaps: Vec::new(),
+            alignment: AudioAlignment::default(),
+        }
+    }
+}
+
+/// What happened to the frames delivered by the audio device during a recording
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureReport {
+    pub captured_frames: u64,
+    pub dropped_frames: u64,
+    pub overruns: u64, // callbacks whose frames did not all fit in the ring buffer
+    pub gaps: Vec<CaptureGap>,
+    // journals written before the alignment have none
+    #[serde(default)]
+    pub alignment: AudioAlignment,
+}
+
+/// Where the capture is written
+pub enum CaptureOutput {
+    File(PathBuf),
+    /// Numbered files next to `filepath`, each sent once complete, the last one when the capture finishes.
+    /// With `segment_frames` the capture starts a new file every that many frames and publishes
+    /// its number in `sequence`, without it the capture follows the `sequence` of another one.
+    Segments {
+        filepath: PathBuf,
+        sequence: Arc<AtomicUsize>,
+        segment_frames: Option<u64>,
+        sender: UnboundedSender<CapturedSegment>,
+    },
+}
+
+impl CaptureOutput {
+    fn first_filepath(&self) -> PathBuf {
+        match self {
+            CaptureOutput::File(filepath) => filepath.clone(),
+            CaptureOutput::Segments { filepath, .. } => segment_filepath(filepath, 0),
+        }
+    }
+}
+
+/// A complete segment of a segmented capture
+#[derive(Clone, Debug)]
+pub struct CapturedSegment {
+    pub sequence: usize,
+    pub filepath: PathBuf,
+    // the capture finished with it
+    pub last: bool,
+    // where the frames with a known capture time landed in it, counted from the start of the capture
+    pub alignment: AudioAlignment,
+}
+
+/// `output.wav` becomes `output.3.wav`
+pub fn segment_filepath(filepath: &Path, sequence: usize) -> PathBuf {
+    filepath.with_extension(format!("{}.wav", sequence))
+}
+
+// the gaps of the capture, and where frames with a known capture time were written
+type WriterResult = Result<(Vec<CaptureGap>, Vec<ClockAnchor>), hound::Error>;
+
+/// Capture time of the frame pushed after `frame` others by the input
+#[derive(Clone, Copy)]
+struct ClockMarker {
+    frame: u64,
+    time: SystemTime,
+}
+
+/// Producing end of the capture, owned by the cpal callback
+pub struct CaptureInput {
+    producer: Producer<f32>,
+    channels: usize,
+    counters: Arc<CaptureCounters>,
+    markers: Producer<ClockMarker>,
+    clock: RecordingClock,
+    pushed_frames: u64,
+    next_marker_frame: u64,
+    marker_frames: u64,
+}
+
+impl CaptureInput {
+    /// Runs on the real-time audio thread: it never locks, allocates or panics.
+    /// Frames that do not fit in the ring buffer are dropped whole and counted.
+    pub fn push<T>(&mut self, data: &[T], info: &cpal::InputCallbackInfo)
+    where
+        T: Sample,
+        f32: FromSample<T>,
+    {
+        let clock = self.clock;
+        self.push_frames(data, || {
+            // the device tells how long before the callback the buffer was captured
+            let timestamp = info.timestamp();
+            let latency = timestamp
+                .callback
+                .duration_since(&timestamp.capture)
+                .unwrap_or_default();
+            clock.now() - latency
+        });
+    }
+
+    /// Pushes a buffer whose first frame was captured at `captured_at`, only read when a marker is due
+    fn push_frames<T>(&mut self, data: &[T], captured_at: impl FnOnce() -> SystemTime)
+    where
+        T: Sample,
+        f32: FromSample<T>,
+    {
+        if self.counters.paused.load(Ordering::Relaxed) {
+            // what is captured after the pause gets its own capture time
+            self.next_marker_frame = self.pushed_frames;
+            return;
+        }
+
+        if self.pushed_frames >= self.next_marker_frame {
+            let _ = self.markers.push(ClockMarker {
+                frame: self.pushed_frames,
+                time: captured_at(),
+            });
+            self.next_marker_frame = self.pushed_frames + self.marker_frames;
+        }
+
+        let frames = data.len() / self.channels;
+        let written_frames = (self.producer.slots() / self.channels).min(frames);
+        if let Ok(chunk) = self
+            .producer
+            .write_chunk_uninit(written_frames * self.channels)
+        {
+            chunk.fill_from_iter(data.iter().map(|sample| sample.to_sample::<f32>()));
+        }
+
+        self.pushed_frames += written_frames as u64;
+        self.counters
+            .captured_frames
+            .fetch_add(written_frames as u64, Ordering::Relaxed);
+        if written_frames < frames {
+            self.counters
+                .dropped_frames
+                .fetch_add((frames - written_frames) as u64, Ordering::Relaxed);
+            self.counters.overruns.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Changes of the device feeding a capture, taken by its writer once the previous one is drained
+enum InputChange {
+    Lost,
+    Replaced(CaptureSource),
+}
+
+/// Consuming end of a capture input, handed to the writer when the input replaces another
+pub struct CaptureSource {
+    consumer: Consumer<f32>,
+    channels: usize,
+    markers: Consumer<ClockMarker>,
+}
+
+/// Time of a capture without a device, filled with silence
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureGap {
+    pub start_ms: u64,
+    pub duration_ms: u64,
+}
+
+/// Consuming end of the capture, a thread draining the ring buffer into a float wav file
+pub struct CaptureWriter {
+    thread: JoinHandle<WriterResult>,
+    counters: Arc<CaptureCounters>,
+    changes: mpsc::Sender<InputChange>,
+    sample_rate: u32,
+    clock: RecordingClock,
+}
+
+impl CaptureWriter {
+    /// Waits for the ring buffer to be drained, the input must have been dropped before
+    pub fn finish(self) -> Result<CaptureReport, Box<dyn std::error::Error + Send + Sync>> {
+        // a lost input is not waited for anymore
+        drop(self.changes);
+        let (gaps, anchors) = self
+            .thread
+            .join()
+            .map_err(|_| "Audio writer thread panicked")??;
+        Ok(CaptureReport {
+            gaps,
+            alignment: AudioAlignment {
+                sample_rate: self.sample_rate,
+                anchors,
+            },
+            ..self.counters.report()
+        })
+    }
+
+    pub fn captured_frames(&self) -> u64 {
+        self.counters.captured_frames.load(Ordering::Relaxed)
+    }
+
+    /// Suspends or resumes the capture, the file stays open and goes on where it stopped
+    pub fn set_paused(&self, paused: bool) {
+        self.counters.paused.store(paused, Ordering::Relaxed);
+    }
+
+    /// Tells the writer to fill the time with silence until the input is replaced,
+    /// before the stream of the lost input is dropped
+    pub fn mark_input_lost(&self) {
+        let _ = self.changes.send(InputChange::Lost);
+    }
+
+    /// A new input for the capture, from a device with possibly another number of channels.
+    /// Its source is given to `replace_input` once its stream plays.
+    pub fn new_input(&self, channels: u16) -> (CaptureInput, CaptureSource) {
+        capture_input(
+            self.counters.clone(),
+            channels,
+            self.sample_rate,
+            self.clock,
+        )
+    }
+
+    pub fn replace_input(&self, source: CaptureSource) {
+        let _ = self.changes.send(InputChange::Replaced(source));
+    }
+}
+
+/// The two ends of the ring buffer between a device and the writer
+fn capture_input(
+    counters: Arc<CaptureCounters>,
+    channels: u16,
+    sample_rate: u32,
+    clock: RecordingClock,
+) -> (CaptureInput, CaptureSource) {
+    let channels = channels.max(1) as usize;
+    let (producer, consumer) = RingBuffer::new(sample_rate as usize * channels * BUFFER_SECONDS);
+    let (markers, marker_consumer) = RingBuffer::new(MARKER_CAPACITY);
+    let input = CaptureInput {
+        producer,
+        channels,
+        counters,
+        markers,
+        clock,
+        pushed_frames: 0,
+        next_marker_frame: 0,
+        marker_frames: sample_rate as u64 * MARKER_SECONDS,
+    };
+    let source = CaptureSource {
+        consumer,
+        channels,
+        markers: marker_consumer,
+    };
+    (input, source)
+}
+
+pub fn start_capture(
+    app_handle: &AppHandle,
+    output: CaptureOutput,
+    channels: u16,
+    sample_rate: u32,
+    level_meter: Option<LevelMeter>,
+) -> Result<(CaptureInput, CaptureWriter), Box<dyn std::error::Error + Send + Sync>> {
+    let clock = app_handle
+        .try_state::<AppState>()
+        .ok_or("Failed to get AppState")?
+        .recording_clock;
+    let spec = WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    };
+    let file = CaptureFile {
+        writer: WavWriter::create(output.first_filepath(), spec)?,
+        output,
+        sequence: 0,
+        segment_samples: 0,
+        written_samples: 0,
+        anchors: Vec::new(),
+        sent_anchors: 0,
+    };
+
+    let counters = Arc::new(CaptureCounters::default());
+    let (changes, thread_changes) = mpsc::channel();
+    let (input, source) = capture_input(counters.clone(), channels, sample_rate, clock);
+
+    let app_handle = app_handle.clone();
+    let thread_counters = counters.clone();
+    let thread = thread::Builder::new()
+        .name("audio-writer".to_string())
+        .spawn(move || {
+            write_loop(
+                &app_handle,
+                source,
+                thread_changes,
+                file,
+                level_meter,
+                &thread_counters,
+            )
+        })?;
+
+    Ok((
+        input,
+        CaptureWriter {
+            thread,
+            counters,
+            changes,
+            sample_rate,
+            clock,
+        },
+    ))
+}
+
+/// Opens an input stream on the device feeding the capture input
+pub fn build_capture_stream(
+    device: &cpal::Device,
+    config: SupportedStreamConfig,
+    input: CaptureInput,
+    err_fn: impl FnMut(cpal::StreamError) + Send + 'static,
+) -> Result<cpal::Stream, Box<dyn std::error::Error + Send + Sync>> {
+    let sample_format = config.sample_format();
+    let config: cpal::StreamConfig = config.into();
+    // every format is converted to the float samples of the capture in the callback
+    let stream = match sample_format {
+        cpal::SampleFormat::I8 => build_input_stream::<i8>(device, &config, input, err_fn)?,
+        cpal::SampleFormat::I16 => build_input_stream::<i16>(device, &config, input, err_fn)?,
+        cpal::SampleFormat::I32 => build_input_stream::<i32>(device, &config, input, err_fn)?,
+        cpal::SampleFormat::I64 => build_input_stream::<i64>(device, &config, input, err_fn)?,
+        cpal::SampleFormat::U8 => build_input_stream::<u8>(device, &config, input, err_fn)?,
+        cpal::SampleFormat::U16 => build_input_stream::<u16>(device, &config, input, err_fn)?,
+        cpal::SampleFormat::U32 => build_input_stream::<u32>(device, &config, input, err_fn)?,
+        cpal::SampleFormat::U64 => build_input_stream::<u64>(device, &config, input, err_fn)?,
+        cpal::SampleFormat::F32 => build_input_stream::<f32>(device, &config, input, err_fn)?,
+        cpal::SampleFormat::F64 => build_input_stream::<f64>(device, &config, input, err_fn)?,
+        sample_format => {
+            return Err(format!("Unsupported sample format '{:?}'", sample_format).into())
+        }
+    };
+    Ok(stream)
+}
+
+fn build_input_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mut input: CaptureInput,
+    err_fn: impl FnMut(cpal::StreamError) + Send + 'static,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    device.build_input_stream(
+        config,
+        move |data: &[T], info: &cpal::InputCallbackInfo| input.push(data, info),
+        err_fn,
+        None,
+    )
+}
+
+pub fn log_stream_error(err: cpal::StreamError) {
+    eprintln!("an error occurred on stream: {}", err);
+}
+
+/// Records a few seconds from the device into a float wav file, blocking the calling thread
+pub fn record_sample(
+    app_handle: &AppHandle,
+    device: &cpal::Device,
+    config: SupportedStreamConfig,
+    duration: Duration,
+    filepath: &Path,
+) -> Result<CaptureReport, Box<dyn std::error::Error + Send + Sync>> {
+    let (input, writer) = start_capture(
+        app_handle,
+        CaptureOutput::File(filepath.to_path_buf()),
+        config.channels(),
+        config.sample_rate().0,
+        None,
+    )?;
+
+    let stream = build_capture_stream(device, config, input, log_stream_error)?;
+    stream.play()?;
+    thread::sleep(duration);
+    drop(stream);
+
+    writer.finish()
+}
+
+/// Finalizes the current segment, sends it with its anchors and opens the next one
+fn next_segment(
+    writer: &mut WavWriter<BufWriter<File>>,
+    filepath: &Path,
+    sequence: &mut usize,
+    anchors: &[ClockAnchor],
+    sender: &UnboundedSender<CapturedSegment>,
+) -> Result<(), hound::Error> {
+    let next_writer = WavWriter::create(segment_filepath(filepath, *sequence + 1), writer.spec())?;
+    std::mem::replace(writer, next_writer).finalize()?;
+
+    // nobody listening anymore is not an error of the capture
+    let _ = sender.send(CapturedSegment {
+        sequence: *sequence,
+        filepath: segment_filepath(filepath, *sequence),
+        last: false,
+        alignment: AudioAlignment {
+            sample_rate: writer.spec().sample_rate,
+            anchors: anchors.to_vec(),
+        },
+    });
+    *sequence += 1;
+    Ok(())
+}
+
+/// The wav file of the capture, or of its current segment
+struct CaptureFile {
+    writer: WavWriter<BufWriter<File>>,
+    output: CaptureOutput,
+    sequence: usize,
+    segment_samples: u64,
+    written_samples: u64,
+    // where frames with a known capture time were written, the first ones were sent with their segments
+    anchors: Vec<ClockAnchor>,
+    sent_anchors: usize,
+}
+
+impl CaptureFile {
+    fn write_sample(&mut self, sample: f32) -> Result<(), hound::Error> {
+        self.writer.write_sample(sample)?;
+        self.written_samples += 1;
+
+        // the header follows the file every second, so that a crash leaves a readable file behind
+        let spec = self.writer.spec();
+        if self
+            .written_samples
+            .is_multiple_of(spec.sample_rate as u64 * spec.channels as u64)
+        {
+            self.writer.flush()?;
+        }
+
+        if let CaptureOutput::Segments {
+            filepath,
+            sequence: published_sequence,
+            segment_frames: Some(segment_frames),
+            sender,
+        } = &self.output
+        {
+            self.segment_samples += 1;
+            if self.segment_samples == segment_frames * spec.channels as u64 {
+                next_segment(
+                    &mut self.writer,
+                    filepath,
+                    &mut self.sequence,
+                    &self.anchors[self.sent_anchors..],
+                    sender,
+                )?;
+                self.sent_anchors = self.anchors.len();
+                published_sequence.store(self.sequence, Ordering::Release);
+                self.segment_samples = 0;
+            }
+        }
+        Ok(())
+    }
+
+    /// A following capture moves to the next segment with the one it follows
+    fn follow(&mut self) -> Result<(), hound::Error> {
+        if let CaptureOutput::Segments {
+            filepath,
+            sequence: leader_sequence,
+            segment_frames: None,
+            sender,
+        } = &self.output
+        {
+            while self.sequence < leader_sequence.load(Ordering::Acquire) {
+                next_segment(
+                    &mut self.writer,
+                    filepath,
+                    &mut self.sequence,
+                    &self.anchors[self.sent_anchors..],
+                    sender,
+                )?;
+                self.sent_anchors = self.anchors.len();
+            }
+        }
+        Ok(())
+    }
+
+    /// Finalizes the file, the last segment of a segmented capture is sent too.
+    /// Returns the anchors of the whole capture.
+    fn finish(self) -> Result<Vec<ClockAnchor>, hound::Error> {
+        let sample_rate = self.writer.spec().sample_rate;
+        self.writer.finalize()?;
+        if let CaptureOutput::Segments {
+            filepath, sender, ..
+        } = &self.output
+        {
+            let _ = sender.send(CapturedSegment {
+                sequence: self.sequence,
+                filepath: segment_filepath(filepath, self.sequence),
+                last: true,
+                alignment: AudioAlignment {
+                    sample_rate,
+                    anchors: self.anchors[self.sent_anchors..].to_vec(),
+                },
+            });
+        }
+        Ok(self.anchors)
+    }
+
+    fn position_ms(&self) -> u64 {
+        let spec = self.writer.spec();
+        self.written_samples / spec.channels as u64 * 1000 / spec.sample_rate as u64
+    }
+}
+
+/// A gap being filled, from the moment the lost input stopped delivering
+struct OpenGap {
+    start_ms: u64,
+    checked: Instant,
+    duration: Duration,
+    frames: u64,
+}
+
+impl OpenGap {
+    /// Writes the silence the time since the input was lost calls for, pauses excepted
+    fn fill(&mut self, file: &mut CaptureFile, paused: bool) -> Result<(), hound::Error> {
+        let now = Instant::now();
+        if !paused {
+            self.duration += now - self.checked;
+        }
+        self.checked = now;
+
+        let spec = file.writer.spec();
+        let frames = (self.duration.as_secs_f64() * spec.sample_rate as f64) as u64;
+        while self.frames < frames {
+            for _ in 0..spec.channels {
+                file.write_sample(0.0)?;
+            }
+            self.frames += 1;
+        }
+        Ok(())
+    }
+
+    fn close(mut self, file: &mut CaptureFile, paused: bool) -> Result<CaptureGap, hound::Error> {
+        self.fill(file, paused)?;
+        Ok(CaptureGap {
+            start_ms: self.start_ms,
+            duration_ms: self.frames * 1000 / file.writer.spec().sample_rate as u64,
+        })
+    }
+}
+
+fn write_loop(
+    app_handle: &AppHandle,
+    mut source: CaptureSource,
+    changes: mpsc::Receiver<InputChange>,
+    mut file: CaptureFile,
+    mut level_meter: Option<LevelMeter>,
+    counters: &CaptureCounters,
+) -> WriterResult {
+    let channels = file.writer.spec().channels as usize;
+    let mut frame = Vec::with_capacity(channels);
+    let mut last_read = Instant::now();
+    let mut gap: Option<OpenGap> = None;
+    let mut gaps = Vec::new();
+    // frames read from the current source, which its markers count in
+    let mut source_frames = 0;
+    let mut reported_overruns = 0;
+    loop {
+        file.follow()?;
+
+        // checked before reading the slots, so that nothing pushed before the input was dropped is missed
+        let abandoned = source.consumer.is_abandoned();
+        let available = source.consumer.slots();
+        if available == 0 {
+            // nothing is missing from the capture while it is paused
+            let paused = counters.paused.load(Ordering::Relaxed);
+            if paused {
+                last_read = Instant::now();
+            }
+
+            match changes.try_recv() {
+                Ok(InputChange::Lost) => {
+                    gap.get_or_insert(OpenGap {
+                        start_ms: file.position_ms(),
+                        checked: last_read,
+                        duration: Duration::ZERO,
+                        frames: 0,
+                    });
+                    continue;
+                }
+                Ok(InputChange::Replaced(next_source)) => {
+                    if let Some(gap) = gap.take() {
+                        gaps.push(gap.close(&mut file, paused)?);
+                    }
+                    source = next_source;
+                    source_frames = 0;
+                    frame.clear();
+                    last_read = Instant::now();
+                    continue;
+                }
+                // the recording stopped while the input was lost
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    if let Some(gap) = gap.take() {
+                        gaps.push(gap.close(&mut file, paused)?);
+                        break;
+                    }
+                }
+                Err(mpsc::TryRecvError::Empty) => {}
+            }
+
+            // the timeline goes on without a device, so that what is captured next stays in place
+            if let Some(gap) = gap.as_mut() {
+                gap.fill(&mut file, paused)?;
+            } else if abandoned {
+                break;
+            }
+            thread::sleep(WRITER_INTERVAL);
+            continue;
+        }
+
+        if let Ok(chunk) = source.consumer.read_chunk(available) {
+            let (first, second) = chunk.as_slices();
+            for sample in first.iter().chain(second) {
+                frame.push(*sample);
+                if frame.len() < source.channels {
+                    continue;
+                }
+
+                // where a frame with a known capture time lands in the file
+                while let Ok(marker) = source.markers.peek() {
+                    if marker.frame > source_frames {
+                        break;
+                    }
+                    let frame_index = file.written_samples / channels as u64;
+                    file.anchors
+                        .push(ClockAnchor::new(frame_index, marker.time));
+                    let _ = source.markers.pop();
+                }
+                source_frames += 1;
+
+                // a replacing device may not have the channels of the first one
+                let mono = frame.iter().sum::<f32>() / source.channels as f32;
+                let samples = (0..channels).map(|channel| {
+                    if source.channels == channels {
+                        frame[channel]
+                    } else {
+                        mono
+                    }
+                });
+                for sample in samples {
+                    file.write_sample(sample)?;
+
+                    // throttled by the meter to one event per window
+                    if let Some((level, warnings)) =
+                        level_meter.as_mut().and_then(|meter| meter.push(sample))
+                    {
+                        let _ = app_handle.emit_to("main", "audio-level", level);
+                        for warning in warnings {
+                            let _ = app_handle.emit_to("main", "audio-warning", warning);
+                        }
+                    }
+                }
+                frame.clear();
+            }
+            chunk.commit_all();
+            last_read = Instant::now();
+        }
+
+        let report = counters.report();
+        if report.overruns > reported_overruns {
+            reported_overruns = report.overruns;
+            eprintln!(
+                "audio overrun: {} frames dropped so far",
+                report.dropped_frames
+            );
+            let _ = app_handle.emit("audio-overrun", report);
+        }
+    }
+
+    // a following capture ends on the segment of the one it follows
+    file.follow()?;
+    let anchors = file.finish()?;
+    Ok((gaps, anchors))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overfilled_ring_drops_whole_frames() {
+        let counters = Arc::new(CaptureCounters::default());
+        // 2 channels at 100 Hz, the ring holds 200 frames
+        let (mut input, mut source) =
+            capture_input(counters.clone(), 2, 100, RecordingClock::default());
+
+        input.push_frames(&[0.5f32; 300], SystemTime::now);
+        let report = counters.report();
+        assert_eq!((report.captured_frames, report.dropped_frames), (150, 0));
+
+        // only 50 of the next 100 frames fit
+        input.push_frames(&[0.25f32; 200], SystemTime::now);
+        let report = counters.report();
+        assert_eq!(report.captured_frames, 200);
+        assert_eq!(report.dropped_frames, 50);
+        assert_eq!(report.overruns, 1);
+        assert_eq!(source.consumer.slots(), 400);
+
+        // the frames that fit are whole, the last one included
+        let chunk = source.consumer.read_chunk(400).unwrap();
+        let (first, second) = chunk.as_slices();
+        let samples: Vec<f32> = first.iter().chain(second).copied().collect();
+        assert!(samples[..300].iter().all(|sample| *sample == 0.5));
+        assert!(samples[300..].iter().all(|sample| *sample == 0.25));
+
+        // a pause drops nothing, its frames are not captured at all
+        counters.paused.store(true, Ordering::Relaxed);
+        input.push_frames(&[0.0f32; 100], SystemTime::now);
+        assert_eq!(counters.report().dropped_frames, 50);
+    }
+
+    #[test]
+    fn lost_input_is_filled_with_silence_but_pauses() {
+        let filepath =
+            std::env::temp_dir().join(format!("sniive_capture_gap_{}.wav", std::process::id()));
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 1000,
+            bits_per_sample: 32,
+            sample_format: SampleFormat::Float,
+        };
+        let mut file = CaptureFile {
+            writer: WavWriter::create(&filepath, spec).unwrap(),
+            output: CaptureOutput::File(filepath.clone()),
+            sequence: 0,
+            segment_samples: 0,
+            written_samples: 0,
+            anchors: Vec::new(),
+            sent_anchors: 0,
+        };
+        for _ in 0..1000 {
+            file.write_sample(0.5).unwrap();
+        }
+
+        // lost for 300ms, then paused for a second
+        let mut gap = OpenGap {
+            start_ms: file.position_ms(),
+            checked: Instant::now() - Duration::from_millis(300),
+            duration: Duration::ZERO,
+            frames: 0,
+        };
+        gap.fill(&mut file, false).unwrap();
+        assert!((300..350).contains(&gap.frames), "{}", gap.frames);
+        assert_eq!(file.written_samples, 1000 + gap.frames * 2);
+
+        let filled = gap.frames;
+        gap.checked = Instant::now() - Duration::from_secs(1);
+        gap.fill(&mut file, true).unwrap();
+        assert_eq!(gap.frames, filled);
+
+        let closed = gap.close(&mut file, true).unwrap();
+        assert_eq!(closed.start_ms, 500);
+        assert_eq!(closed.duration_ms, filled);
+        assert_eq!(file.position_ms(), 500 + filled);
+        file.finish().unwrap();
+
+        let reader = hound::WavReader::open(&filepath).unwrap();
+        let samples: Vec<f32> = reader.into_samples().map(Result::unwrap).collect();
+        assert!(samples[..1000].iter().all(|sample| *sample == 0.5));
+        assert!(samples[1000..].iter().all(|sample| *sample == 0.0));
+        std::fs::remove_file(filepath).unwrap();
+    }
+}
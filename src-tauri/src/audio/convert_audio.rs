@@ -1,74 +1,423 @@
-use std::path::PathBuf;
-
-use circular_queue::CircularQueue;
-use hound::{SampleFormat, WavSpec};
-use wavers::{read, write, Samples};
-
-pub fn convert_audio(filepath: &PathBuf, base_spec: &WavSpec) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let samples = match (base_spec.sample_format, base_spec.bits_per_sample) {
-        (SampleFormat::Int, 16) => {
-            let (samples, _): (Samples<i16>,i32) = read::<i16, _>(filepath.clone())?;
-            let converted_samples: Samples<f64> = samples.convert::<f64>();
-            Ok::<Samples<f64>, &str>(converted_samples)
+use std::{path::PathBuf, time::Duration};
+
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    audio_settings::{AudioSettings, SystemAudioMode},
+    dsp_chain::DspChain,
+    ducking::KeyboardDucker,
+    limiter::{Limiter, TruePeakMeter},
+    loudness::{db_to_gain, gain_to_db, LoudnessMeter, LoudnessReport},
+    mix::{AlignedTrack, SystemTrack},
+    resample::Resampler,
+    trim::{RemovedRange, TrimPlan},
+    vad::{SpeechSegment, VoiceActivityDetector},
+};
+
+pub const OUTPUT_SAMPLE_RATE: u32 = 16000;
+// frames read at once, this bounds the memory used whatever the length of the recording
+const CHUNK_FRAMES: usize = 8192;
+// the gain of a segment moves from the one of the previous segment over this time
+const GAIN_RAMP_SECONDS: f64 = 1.0;
+
+type SampleIterator = Box<dyn Iterator<Item = Result<f64, hound::Error>> + Send>;
+
+/// Reads a wav file by chunks of frames, downmixed to mono samples in [-1, 1]
+pub struct MonoReader {
+    samples: SampleIterator,
+    channels: usize,
+    pub sample_rate: u32,
+}
+
+impl MonoReader {
+    pub fn open(
+        filepath: &PathBuf,
+    ) -> Result<MonoReader, Box<dyn std::error::Error + Send + Sync>> {
+        let reader = WavReader::open(filepath)?;
+        let spec = reader.spec();
+        let samples: SampleIterator = match (spec.sample_format, spec.bits_per_sample) {
+            (SampleFormat::Int, 16) => Box::new(
+                reader
+                    .into_samples::<i16>()
+                    .map(|sample| sample.map(|sample| sample as f64 / 32768.0)),
+            ),
+            (SampleFormat::Int, bits @ (24 | 32)) => {
+                let scale = (1u64 << (bits - 1)) as f64;
+                Box::new(
+                    reader
+                        .into_samples::<i32>()
+                        .map(move |sample| sample.map(|sample| sample as f64 / scale)),
+                )
+            }
+            (SampleFormat::Float, 32) => Box::new(
+                reader
+                    .into_samples::<f32>()
+                    .map(|sample| sample.map(|sample| sample as f64)),
+            ),
+            sample_format => {
+                return Err(format!("Unsupported sample format '{:?}'", sample_format).into())
+            }
+        };
+
+        Ok(MonoReader {
+            samples,
+            channels: spec.channels.max(1) as usize,
+            sample_rate: spec.sample_rate,
+        })
+    }
+
+    /// Replaces the content of `chunk` with the next frames, returns false once the file is over
+    pub fn read_chunk(&mut self, chunk: &mut Vec<f64>) -> Result<bool, hound::Error> {
+        chunk.clear();
+        'frames: while chunk.len() < CHUNK_FRAMES {
+            // if the audio is stereo, we need to average values 2 by 2 to get mono samples
+            let mut sum = 0.0;
+            for _ in 0..self.channels {
+                match self.samples.next() {
+                    Some(sample) => sum += sample?,
+                    // an incomplete last frame is dropped
+                    None => break 'frames,
+                }
+            }
+            chunk.push(sum / self.channels as f64);
+        }
+        Ok(!chunk.is_empty())
+    }
+}
+
+/// What the conversion measured and did, uploaded with the recording
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversionReport {
+    pub loudness: LoudnessReport,
+    pub speech_segments: Vec<SpeechSegment>,
+    pub removed_ranges: Vec<RemovedRange>,
+    // length of the converted audio, once the removed ranges are cut
+    pub duration_ms: u64,
+    pub ducked_keystrokes: usize,
+    // the system audio was converted on its own instead of being mixed with the microphone
+    pub separate_system_track: bool,
+}
+
+fn to_i16(sample: f64) -> i16 {
+    (sample.clamp(-1.0, 1.0) * i16::MAX as f64).round() as i16
+}
+
+/// Converts the recording to 16kHz mono 16-bit audio in `output_path`, the recording itself is left as it is.
+/// `keystrokes` are the key events of the recording, as offsets from the start of the audio.
+/// The system audio track, if any, is mixed with the microphone or converted alongside it.
+pub fn convert_audio(
+    filepath: &PathBuf,
+    output_path: &PathBuf,
+    settings: &AudioSettings,
+    keystrokes: &[Duration],
+    system_track: Option<&SystemTrack>,
+) -> Result<ConversionReport, Box<dyn std::error::Error + Send + Sync>> {
+    SegmentConverter::new(settings.clone()).convert(
+        filepath,
+        output_path,
+        Duration::ZERO,
+        keystrokes,
+        system_track,
+        true,
+    )
+}
+
+/// Converts a recording one segment after the other, each to 16kHz mono 16-bit audio.
+/// The first pass downmixes, resamples and cleans up into an intermediate float file
+/// while measuring the integrated loudness and finding speech, the second pass normalizes it to the target
+/// through a true-peak limiter, ducks the keystrokes heard between speech and cuts
+/// the silences the settings ask to remove, so only a chunk of samples is ever held in memory.
+/// The filters, the speech detection, the loudness measured so far and the gain go on from a segment
+/// to the next, so that the level does not jump at the boundaries. What the filters hold back
+/// at the end of a segment starts the next one, `next_start` tells where.
+pub struct SegmentConverter {
+    settings: AudioSettings,
+    // made with the sample rate of the first segment, both are flushed with the last one
+    resampler: Option<Resampler>,
+    dsp_chain: Option<DspChain>,
+    loudness_meter: LoudnessMeter,
+    detector: VoiceActivityDetector,
+    limiter: Limiter,
+    system: Option<AlignedTrack>,
+    // gain of the end of the last segment
+    gain_db: Option<f64>,
+    // position of the first segment in the recording, and samples converted since
+    origin: Option<Duration>,
+    converted: usize,
+}
+
+impl SegmentConverter {
+    pub fn new(settings: AudioSettings) -> SegmentConverter {
+        SegmentConverter {
+            resampler: None,
+            dsp_chain: Some(DspChain::new(OUTPUT_SAMPLE_RATE, &settings.dsp)),
+            loudness_meter: LoudnessMeter::new(OUTPUT_SAMPLE_RATE),
+            detector: VoiceActivityDetector::new(
+                OUTPUT_SAMPLE_RATE,
+                settings.silence.vad_threshold_db,
+            ),
+            limiter: Limiter::new(
+                OUTPUT_SAMPLE_RATE,
+                db_to_gain(settings.loudness.true_peak_ceiling_dbtp),
+            ),
+            system: None,
+            gain_db: None,
+            origin: None,
+            converted: 0,
+            settings,
+        }
+    }
+
+    /// Position in the recording of the audio the next segment converts to
+    pub fn next_start(&self) -> Option<Duration> {
+        self.origin.map(|origin| {
+            origin + Duration::from_secs_f64(self.converted as f64 / OUTPUT_SAMPLE_RATE as f64)
+        })
+    }
+
+    /// Converts the next segment in `output_path`, the segment itself is left as it is.
+    /// `start` is its position in the recording, `keystrokes` the key events heard in it as offsets from there.
+    /// Its part of the system audio track, if any, is mixed with the microphone or converted alongside it.
+    /// The `last` one outputs what the filters still hold.
+    pub fn convert(
+        &mut self,
+        filepath: &PathBuf,
+        output_path: &PathBuf,
+        start: Duration,
+        keystrokes: &[Duration],
+        system_track: Option<&SystemTrack>,
+        last: bool,
+    ) -> Result<ConversionReport, Box<dyn std::error::Error + Send + Sync>> {
+        let SegmentConverter {
+            settings,
+            resampler,
+            dsp_chain,
+            loudness_meter,
+            detector,
+            limiter,
+            system,
+            gain_db: last_gain_db,
+            origin,
+            converted,
+        } = self;
+        let Some(dsp_stage) = dsp_chain.as_mut() else {
+            return Err("The recording was already converted to its end".into());
+        };
+        origin.get_or_insert(start);
+
+        let intermediate_path = output_path.with_extension("mono.wav");
+        let float_spec = WavSpec {
+            channels: 1,
+            sample_rate: OUTPUT_SAMPLE_RATE,
+            bits_per_sample: 32,
+            sample_format: SampleFormat::Float,
+        };
+        let output_spec = WavSpec {
+            channels: 1,
+            sample_rate: OUTPUT_SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+
+        // the system track goes on from the files of the previous segments
+        let system_settings = &settings.system_audio;
+        if let Some(track) = system_track {
+            match system.as_mut() {
+                Some(system) => system.append(&track.filepath)?,
+                None => *system = Some(AlignedTrack::open(track)?),
+            }
+            if let Some(system) = system.as_mut().filter(|_| last) {
+                system.complete();
+            }
         }
-        (SampleFormat::Int, 32) => {
-            let (samples, _): (Samples<i32>,i32) = read::<i32, _>(filepath.clone())?;
-            let converted_samples: Samples<f64> = samples.convert::<f64>();
-            Ok::<Samples<f64>, &str>(converted_samples)
+        let mut system = system_track.and(system.as_mut());
+        let separate_system_track =
+            system.is_some() && system_settings.mode == SystemAudioMode::SeparateTrack;
+        let system_intermediate_path =
+            system_track.map(|track| track.output_path.with_extension("mono.wav"));
+        let mut system_intermediate = match (&system_intermediate_path, separate_system_track) {
+            (Some(path), true) => Some(WavWriter::create(path, float_spec)?),
+            _ => None,
+        };
+        let microphone_gain = if system.is_some() && !separate_system_track {
+            db_to_gain(system_settings.microphone_level_db)
+        } else {
+            1.0
+        };
+        let system_gain = db_to_gain(system_settings.system_level_db);
+
+        let mut reader = MonoReader::open(filepath)?;
+        let resampler_stage =
+            resampler.get_or_insert_with(|| Resampler::new(reader.sample_rate, OUTPUT_SAMPLE_RATE));
+        let mut length = 0;
+        let mut intermediate = WavWriter::create(&intermediate_path, float_spec)?;
+
+        let mut chunk = Vec::with_capacity(CHUNK_FRAMES);
+        let mut resampled = Vec::with_capacity(CHUNK_FRAMES);
+        let mut processed = Vec::with_capacity(CHUNK_FRAMES);
+        let mut system_samples = Vec::with_capacity(CHUNK_FRAMES);
+        let mut measure_samples = |samples: &[f64]| -> Result<(), hound::Error> {
+            system_samples.clear();
+            match system.as_mut() {
+                Some(system) => system.read(samples.len(), &mut system_samples)?,
+                None => system_samples.resize(samples.len(), 0.0),
+            }
+
+            for (sample, system_sample) in samples.iter().zip(system_samples.iter()) {
+                // speech is only looked for in the microphone
+                detector.push(*sample);
+
+                let system_sample = system_sample * system_gain;
+                let sample = match system_intermediate.as_mut() {
+                    Some(system_intermediate) => {
+                        system_intermediate.write_sample(system_sample as f32)?;
+                        *sample
+                    }
+                    None => sample * microphone_gain + system_sample,
+                };
+                loudness_meter.push(sample);
+                intermediate.write_sample(sample as f32)?;
+            }
+            length += samples.len();
+            Ok(())
+        };
+        while reader.read_chunk(&mut chunk)? {
+            resampled.clear();
+            processed.clear();
+            resampler_stage.process(&chunk, &mut resampled);
+            dsp_stage.process(&resampled, &mut processed);
+            measure_samples(&processed)?;
         }
-        (SampleFormat::Float, 32) => {
-            let (samples, _): (Samples<f32>,i32) = read::<f32, _>(filepath.clone())?;
-            let converted_samples: Samples<f64> = samples.convert::<f64>();
-            Ok::<Samples<f64>, &str>(converted_samples)
+        if last {
+            resampled.clear();
+            processed.clear();
+            if let Some(resampler) = resampler.take() {
+                resampler.flush(&mut resampled);
+            }
+            if let Some(mut dsp_chain) = dsp_chain.take() {
+                dsp_chain.process(&resampled, &mut processed);
+                dsp_chain.flush(&mut processed);
+            }
+            measure_samples(&processed)?;
         }
-        sample_format => {
-            return Err(format!("Unsupported sample format '{:?}'", sample_format).into())
+        intermediate.finalize()?;
+        if let Some(system_intermediate) = system_intermediate {
+            system_intermediate.finalize()?;
         }
-    }?;
 
-    // if the audio is stereo, we need to average values 2 by 2 to get mono samples
-    let mono_samples = if base_spec.channels > 0 {
-        let mut mono_samples: Vec<f64> = Vec::with_capacity(samples.len() / base_spec.channels as usize);
-        for i in (0..samples.len()).step_by(base_spec.channels as usize) {
-            mono_samples.push(samples.iter().skip(i).take(base_spec.channels as usize).sum::<f64>() / base_spec.channels as f64);
+        let speech = detector.take_segments(*converted, last);
+        *converted += length;
+        // the edges a segment shares with its neighbours count as speech, so that a pause
+        // over a boundary is shortened like any other instead of being trimmed away
+        let mut kept_speech = speech.clone();
+        if !start.is_zero() {
+            kept_speech.insert(0, (0, 0));
         }
-        Samples::from(mono_samples)
-    } else {
-        samples
-    };
-
-    // the length of the queue is the number of samples needed to make 10ms
-    let queue_length = (base_spec.sample_rate as f64 / 1000.0 * 10.0) as usize;
-    let mut queue: CircularQueue<f64> = CircularQueue::with_capacity(queue_length);
-    let mut rms_squared: f64 = 0.0;
-    let mut max_rms_squared: f64 = 0.0;
-
-    // fill the queue with 0s
-    for _ in 0..queue_length {
-        queue.push(0.0);
-    }
+        if !last {
+            kept_speech.push((length, length));
+        }
+        let mut trim_plan =
+            TrimPlan::new(OUTPUT_SAMPLE_RATE, length, &kept_speech, &settings.silence);
+        let mut system_trim_plan = trim_plan.clone();
+        let mut ducker = KeyboardDucker::new(
+            OUTPUT_SAMPLE_RATE,
+            keystrokes,
+            &speech,
+            &settings.keyboard_ducking,
+        );
+        let ducked_keystrokes = ducker.ducked_windows();
+        drop(reader);
+
+        // the loudness is the one of the recording so far, the gain ramps from the one of the last segment
+        let loudness_settings = &settings.loudness;
+        let integrated_loudness = loudness_meter.integrated_loudness();
+        let gain_db = integrated_loudness.map_or(0.0, |loudness| {
+            (loudness_settings.target_lufs - loudness).min(loudness_settings.max_gain_db)
+        });
+        let start_gain_db = last_gain_db.replace(gain_db).unwrap_or(gain_db);
+        let ramp = (OUTPUT_SAMPLE_RATE as f64 * GAIN_RAMP_SECONDS) as usize;
+        let gain = |position: usize| {
+            let progress = position.min(ramp) as f64 / ramp as f64;
+            db_to_gain(start_gain_db + (gain_db - start_gain_db) * progress)
+        };
 
-    for sample in mono_samples.iter() {
-        if let Some(removed_sample) = queue.push(*sample) {
-            rms_squared += (sample * sample - removed_sample * removed_sample) / queue.len() as f64;
+        let mut output = WavWriter::create(output_path, output_spec)?;
+        // the separate system track gets the same gain and cuts, but no limiter nor ducking
+        let (mut system_samples, mut system_output) =
+            match (system_track, &system_intermediate_path) {
+                (Some(track), Some(path)) if separate_system_track => (
+                    Some(WavReader::open(path)?.into_samples::<f32>()),
+                    Some(WavWriter::create(&track.output_path, output_spec)?),
+                ),
+                _ => (None, None),
+            };
+        let mut true_peak_meter = TruePeakMeter::new();
+        let mut true_peak: f64 = 0.0;
+        let mut position = 0;
+        let mut write_samples = |samples: &[f64]| -> Result<(), hound::Error> {
+            for sample in samples {
+                let trim_gain = trim_plan.gain(position);
+                position += 1;
+                let Some(trim_gain) = trim_gain else {
+                    continue;
+                };
 
-            if rms_squared > max_rms_squared {
-                max_rms_squared = rms_squared;
+                let sample = sample * trim_gain;
+                true_peak = true_peak.max(true_peak_meter.push(sample));
+                output.write_sample(to_i16(sample))?;
             }
-        }
-    }
+            Ok(())
+        };
 
-    let normalization_factor = 0.6 / max_rms_squared.sqrt();
-    let sampling_factor = base_spec.sample_rate as f64 / 16000.0;
+        let mut limited = Vec::with_capacity(CHUNK_FRAMES);
+        for (position, sample) in WavReader::open(&intermediate_path)?
+            .into_samples::<f32>()
+            .enumerate()
+        {
+            let gain = gain(position);
+            limiter.process(sample? as f64 * gain * ducker.gain(position), &mut limited);
 
-    // normalize_samples_vect has a length of the number of samples in the original audio file
-    // divided by the sampling factor
-    let mut normalized_samples_vect: Vec<f64> = vec![0.0; (mono_samples.len() as f64 / sampling_factor).ceil() as usize];
-    for i in 0..normalized_samples_vect.len() {
-        normalized_samples_vect[i] = mono_samples[(i as f64 * sampling_factor) as usize] * normalization_factor;
-    }
-    let normalized_samples: Samples<i16> = Samples::from(normalized_samples_vect).convert::<i16>();
+            if let (Some(system_samples), Some(system_output)) =
+                (system_samples.as_mut(), system_output.as_mut())
+            {
+                if let (Some(system_sample), Some(trim_gain)) =
+                    (system_samples.next(), system_trim_plan.gain(position))
+                {
+                    system_output.write_sample(to_i16(system_sample? as f64 * gain * trim_gain))?;
+                }
+            }
+            if limited.len() >= CHUNK_FRAMES {
+                write_samples(&limited)?;
+                limited.clear();
+            }
+        }
+        limiter.drain(&mut limited);
+        write_samples(&limited)?;
+        output.finalize()?;
+        if let Some(system_output) = system_output {
+            system_output.finalize()?;
+        }
 
-    write(filepath.clone(), &normalized_samples, 16000, 1)?;
-    Ok(())
-}
\ No newline at end of file
+        std::fs::remove_file(intermediate_path)?;
+        if let Some(system_intermediate_path) =
+            system_intermediate_path.filter(|path| path.exists())
+        {
+            std::fs::remove_file(system_intermediate_path)?;
+        }
+        Ok(ConversionReport {
+            loudness: LoudnessReport {
+                integrated_loudness,
+                target_loudness: loudness_settings.target_lufs,
+                gain_db,
+                true_peak_dbtp: gain_to_db(true_peak),
+            },
+            speech_segments: trim_plan.speech_segments(&speech),
+            removed_ranges: trim_plan.removed_ranges(),
+            duration_ms: trim_plan.output_duration_ms(),
+            ducked_keystrokes,
+            separate_system_track,
+        })
+    }
+}
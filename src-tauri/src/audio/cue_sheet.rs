@@ -0,0 +1,133 @@
+use std::ops::Range;
+
+use serde::Serialize;
+
+use super::{
+    audio_settings::AudioFormat, convert_audio::OUTPUT_SAMPLE_RATE, flac::BLOCK_SIZE,
+    trim::RemovedRange,
+};
+
+// header hound writes for 16-bit mono pcm, the samples start right after it
+const WAV_HEADER_BYTES: u64 = 44;
+const BYTES_PER_SAMPLE: u64 = 2;
+
+/// Part of a step heard in one uploaded segment, in milliseconds from the start of its file
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CuePiece {
+    pub sequence: usize,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    // a wav is cut at the sample, a flac at the frames around the piece, which decode after the
+    // stream header, its first 42 bytes. an opus file, or a segment recovered after a crash, is cut by time
+    pub start_byte: Option<u64>,
+    pub end_byte: Option<u64>,
+}
+
+/// Where a step is heard in the uploaded audio, across the segments it spans
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepCue {
+    pub step: usize,
+    pub pieces: Vec<CuePiece>,
+}
+
+/// Position in the uploaded file of a position of the captured segment, once the removed ranges are cut
+pub fn output_ms(removed_ranges: &[RemovedRange], duration_ms: u64, ms: u64) -> u64 {
+    let removed: u64 = removed_ranges
+        .iter()
+        .filter(|range| range.start_ms < ms)
+        .map(|range| range.end_ms.min(ms) - range.start_ms)
+        .sum();
+    (ms - removed).min(duration_ms)
+}
+
+/// The piece of a segment for a range of its captured audio, None if all of it was cut
+pub fn cue_piece(
+    sequence: usize,
+    range: Range<u64>,
+    removed_ranges: &[RemovedRange],
+    duration_ms: u64,
+    format: AudioFormat,
+    frame_offsets: &[u64],
+) -> Option<CuePiece> {
+    let start_ms = output_ms(removed_ranges, duration_ms, range.start);
+    let end_ms = output_ms(removed_ranges, duration_ms, range.end);
+    if start_ms >= end_ms {
+        return None;
+    }
+
+    let sample = |ms: u64| ms * OUTPUT_SAMPLE_RATE as u64 / 1000;
+    let (start_byte, end_byte) = match format {
+        AudioFormat::Wav => (
+            Some(WAV_HEADER_BYTES + sample(start_ms) * BYTES_PER_SAMPLE),
+            Some(WAV_HEADER_BYTES + sample(end_ms) * BYTES_PER_SAMPLE),
+        ),
+        // the frame the piece starts in, and the end of the frame it ends in
+        AudioFormat::Flac => (
+            frame_offsets
+                .get(sample(start_ms) as usize / BLOCK_SIZE)
+                .copied(),
+            frame_offsets
+                .get((sample(end_ms) as usize).div_ceil(BLOCK_SIZE))
+                .copied(),
+        ),
+        AudioFormat::Opus => (None, None),
+    };
+    Some(CuePiece {
+        sequence,
+        start_ms,
+        end_ms,
+        start_byte,
+        end_byte,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cut_ranges_move_the_cue() {
+        let removed = [
+            RemovedRange {
+                start_ms: 0,
+                end_ms: 2000,
+            },
+            RemovedRange {
+                start_ms: 4500,
+                end_ms: 9500,
+            },
+        ];
+        // a step from 3s to 10s keeps what is left of it, 1s to 3s in the file
+        let piece = cue_piece(0, 3000..10000, &removed, 4000, AudioFormat::Wav, &[]).unwrap();
+        assert_eq!((piece.start_ms, piece.end_ms), (1000, 3000));
+        assert_eq!(piece.start_byte, Some(44 + 16000 * 2));
+
+        // a step in a removed range is not heard at all
+        assert_eq!(
+            cue_piece(0, 5000..9000, &removed, 4000, AudioFormat::Flac, &[]),
+            None
+        );
+
+        let piece = cue_piece(0, 3000..20000, &removed, 4000, AudioFormat::Opus, &[]).unwrap();
+        assert_eq!((piece.end_ms, piece.end_byte), (4000, None));
+    }
+
+    #[test]
+    fn flac_pieces_cover_their_frames() {
+        // four frames of 4096 samples and a last one of 2016, 1.15s at 16kHz
+        let frame_offsets = [42, 1042, 2042, 3042, 4042, 4542];
+        // 0.3s to 0.6s is samples 4800 to 9600, in the second and third frames
+        let piece = cue_piece(0, 300..600, &[], 1150, AudioFormat::Flac, &frame_offsets).unwrap();
+        assert_eq!((piece.start_byte, piece.end_byte), (Some(1042), Some(3042)));
+
+        // 1s is in the fourth frame, the end of the file is the end of the stream
+        let piece = cue_piece(0, 1000..2000, &[], 1150, AudioFormat::Flac, &frame_offsets).unwrap();
+        assert_eq!((piece.start_byte, piece.end_byte), (Some(3042), Some(4542)));
+
+        // a recovered segment does not know its frames
+        let piece = cue_piece(0, 300..600, &[], 1150, AudioFormat::Flac, &[]).unwrap();
+        assert_eq!((piece.start_byte, piece.end_byte), (None, None));
+    }
+}
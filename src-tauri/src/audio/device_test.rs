@@ -0,0 +1,131 @@
+use std::{path::PathBuf, time::Duration};
+
+use hound::WavReader;
+use serde::Serialize;
+
+use super::{capture::CaptureReport, loudness::gain_to_db};
+
+// windows the noise floor is measured over
+const WINDOW_MS: u64 = 50;
+// the noise floor is the level the quietest windows stay under
+const NOISE_FLOOR_PERCENTILE: f64 = 0.1;
+const CLIPPING_LEVEL: f32 = 0.999;
+const FLOOR_DB: f64 = -100.0;
+
+/// What a device picked up during a test capture
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InputStatistics {
+    pub sample_rate: u32,
+    pub channels: u16,
+    // frames actually delivered per second, which drivers do not always match with the config
+    pub measured_sample_rate: f64,
+    pub dropped_frames: u64,
+    pub rms_db: f64,
+    pub peak_db: f64,
+    pub noise_floor_db: f64,
+    pub clipped_samples: u64,
+}
+
+/// Measures a float wav file written by a capture of `duration`
+pub fn input_statistics(
+    filepath: &PathBuf,
+    duration: Duration,
+    report: &CaptureReport,
+) -> Result<InputStatistics, Box<dyn std::error::Error + Send + Sync>> {
+    let reader = WavReader::open(filepath)?;
+    let spec = reader.spec();
+    let window_samples =
+        (spec.sample_rate as u64 * spec.channels as u64 * WINDOW_MS / 1000).max(1) as usize;
+
+    let mut sum_squares = 0.0;
+    let mut count = 0;
+    let mut peak = 0.0f32;
+    let mut clipped_samples = 0;
+    let mut window_sum_squares = 0.0;
+    let mut window_count = 0;
+    let mut window_levels = Vec::new();
+    for sample in reader.into_samples::<f32>() {
+        let sample = sample?;
+        let square = sample as f64 * sample as f64;
+        sum_squares += square;
+        count += 1;
+        peak = peak.max(sample.abs());
+        if sample.abs() >= CLIPPING_LEVEL {
+            clipped_samples += 1;
+        }
+
+        window_sum_squares += square;
+        window_count += 1;
+        if window_count == window_samples {
+            window_levels.push((window_sum_squares / window_count as f64).sqrt());
+            window_sum_squares = 0.0;
+            window_count = 0;
+        }
+    }
+
+    window_levels.sort_by(f64::total_cmp);
+    let noise_floor = window_levels
+        .get((window_levels.len() as f64 * NOISE_FLOOR_PERCENTILE) as usize)
+        .copied()
+        .unwrap_or(0.0);
+    let rms = match count {
+        0 => 0.0,
+        _ => (sum_squares / count as f64).sqrt(),
+    };
+
+    Ok(InputStatistics {
+        sample_rate: spec.sample_rate,
+        channels: spec.channels,
+        measured_sample_rate: report.captured_frames as f64 / duration.as_secs_f64(),
+        dropped_frames: report.dropped_frames,
+        rms_db: gain_to_db(rms).max(FLOOR_DB),
+        peak_db: gain_to_db(peak as f64).max(FLOOR_DB),
+        noise_floor_db: gain_to_db(noise_floor).max(FLOOR_DB),
+        clipped_samples,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use hound::{SampleFormat, WavSpec, WavWriter};
+
+    use super::*;
+
+    #[test]
+    fn measures_noise_floor_under_speech() {
+        let filepath =
+            std::env::temp_dir().join(format!("sniive_device_test_{}.wav", std::process::id()));
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 8000,
+            bits_per_sample: 32,
+            sample_format: SampleFormat::Float,
+        };
+        let mut writer = WavWriter::create(&filepath, spec).unwrap();
+        // 1s of -40dB noise, 1s of clipped tone
+        for i in 0..8000 {
+            let sample = if i % 2 == 0 { 0.01 } else { -0.01 };
+            writer.write_sample(sample).unwrap();
+            writer.write_sample(sample).unwrap();
+        }
+        for i in 0..8000 {
+            let sample = (1.5 * (i as f32 * 0.1).sin()).clamp(-1.0, 1.0);
+            writer.write_sample(sample).unwrap();
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let report = CaptureReport {
+            captured_frames: 16000,
+            ..Default::default()
+        };
+        let statistics = input_statistics(&filepath, Duration::from_secs(2), &report).unwrap();
+        std::fs::remove_file(filepath).unwrap();
+
+        assert!((statistics.noise_floor_db + 40.0).abs() < 0.1);
+        assert_eq!(statistics.peak_db, 0.0);
+        assert!(statistics.clipped_samples > 0);
+        assert_eq!(statistics.measured_sample_rate, 8000.0);
+    }
+}
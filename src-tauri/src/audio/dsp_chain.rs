@@ -0,0 +1,73 @@
+use super::{
+    audio_settings::DspSettings, biquad::Biquad, noise_gate::NoiseGate,
+    noise_suppression::NoiseSuppressor,
+};
+
+/// Clean-up stages applied to the narration before it is measured and normalized,
+/// each one only present if enabled in the settings
+pub struct DspChain {
+    high_pass: Option<Biquad>,
+    noise_suppressor: Option<NoiseSuppressor>,
+    noise_gate: Option<NoiseGate>,
+    filtered: Vec<f64>,
+}
+
+impl DspChain {
+    pub fn new(sample_rate: u32, settings: &DspSettings) -> DspChain {
+        let high_pass = &settings.high_pass;
+        let noise_suppression = &settings.noise_suppression;
+        let noise_gate = &settings.noise_gate;
+
+        DspChain {
+            high_pass: high_pass
+                .enabled
+                .then(|| Biquad::high_pass(sample_rate, high_pass.cutoff_hz)),
+            noise_suppressor: noise_suppression
+                .enabled
+                .then(|| NoiseSuppressor::new(noise_suppression.reduction_db)),
+            noise_gate: noise_gate.enabled.then(|| {
+                NoiseGate::new(
+                    sample_rate,
+                    noise_gate.threshold_db,
+                    noise_gate.attenuation_db,
+                    noise_gate.release_ms,
+                )
+            }),
+            filtered: Vec::new(),
+        }
+    }
+
+    /// Appends to `output` the processed samples, the chain may hold some of them back until `flush`
+    pub fn process(&mut self, input: &[f64], output: &mut Vec<f64>) {
+        self.filtered.clear();
+        match self.high_pass.as_mut() {
+            Some(high_pass) => self
+                .filtered
+                .extend(input.iter().map(|sample| high_pass.process(*sample))),
+            None => self.filtered.extend_from_slice(input),
+        }
+
+        let start = output.len();
+        match self.noise_suppressor.as_mut() {
+            Some(noise_suppressor) => noise_suppressor.process(&self.filtered, output),
+            None => output.extend_from_slice(&self.filtered),
+        }
+        self.gate(&mut output[start..]);
+    }
+
+    pub fn flush(mut self, output: &mut Vec<f64>) {
+        let start = output.len();
+        if let Some(noise_suppressor) = self.noise_suppressor.take() {
+            noise_suppressor.flush(output);
+        }
+        self.gate(&mut output[start..]);
+    }
+
+    fn gate(&mut self, samples: &mut [f64]) {
+        if let Some(noise_gate) = self.noise_gate.as_mut() {
+            for sample in samples.iter_mut() {
+                *sample = noise_gate.process(*sample);
+            }
+        }
+    }
+}
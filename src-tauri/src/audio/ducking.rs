@@ -0,0 +1,113 @@
+use std::{ops::Range, time::Duration};
+
+use super::audio_settings::KeyboardDuckingSettings;
+
+// ramps at the edges of a ducked window, so that the attenuation does not click itself
+const RAMP_SECONDS: f64 = 0.002;
+
+/// Attenuates the audio around the recorded keystrokes, outside of speech only
+pub struct KeyboardDucker {
+    windows: Vec<Range<usize>>,
+    ducked_gain: f64,
+    ramp: usize,
+    // index of the window reached by `gain`
+    cursor: usize,
+}
+
+impl KeyboardDucker {
+    /// `keystrokes` are offsets from the start of the audio, `speech` sample ranges from the voice detection
+    pub fn new(
+        sample_rate: u32,
+        keystrokes: &[Duration],
+        speech: &[(usize, usize)],
+        settings: &KeyboardDuckingSettings,
+    ) -> KeyboardDucker {
+        let to_samples = |seconds: f64| (seconds * sample_rate as f64) as usize;
+        let before = to_samples(settings.before_ms as f64 / 1000.0);
+        let after = to_samples(settings.after_ms as f64 / 1000.0);
+
+        let mut windows: Vec<Range<usize>> = Vec::new();
+        if settings.enabled {
+            let mut starts: Vec<usize> = keystrokes
+                .iter()
+                .map(|keystroke| to_samples(keystroke.as_secs_f64()))
+                .collect();
+            starts.sort_unstable();
+
+            // the voice is never touched, even if a key was pressed while talking.
+            // each keystroke is checked on its own, so that one near speech does not take its neighbours with it
+            let keystroke_windows = starts
+                .into_iter()
+                .map(|position| position.saturating_sub(before)..position + after)
+                .filter(|window| {
+                    !speech
+                        .iter()
+                        .any(|(start, end)| window.start < *end && *start < window.end)
+                });
+            for window in keystroke_windows {
+                match windows.last_mut() {
+                    Some(last) if window.start <= last.end => last.end = last.end.max(window.end),
+                    _ => windows.push(window),
+                }
+            }
+        }
+
+        KeyboardDucker {
+            windows,
+            ducked_gain: 10.0_f64.powf(-settings.attenuation_db / 20.0),
+            ramp: to_samples(RAMP_SECONDS).max(1),
+            cursor: 0,
+        }
+    }
+
+    pub fn ducked_windows(&self) -> usize {
+        self.windows.len()
+    }
+
+    /// Gain of the sample at `position`, positions must be given in increasing order
+    pub fn gain(&mut self, position: usize) -> f64 {
+        while self
+            .windows
+            .get(self.cursor)
+            .is_some_and(|window| window.end <= position)
+        {
+            self.cursor += 1;
+        }
+
+        match self.windows.get(self.cursor) {
+            Some(window) if window.contains(&position) => {
+                let edge = (position - window.start).min(window.end - 1 - position);
+                let ramp = (edge as f64 / self.ramp as f64).min(1.0);
+                1.0 + (self.ducked_gain - 1.0) * ramp
+            }
+            _ => 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ducks_keystrokes_outside_speech_only() {
+        let settings = KeyboardDuckingSettings {
+            enabled: true,
+            attenuation_db: 20.0,
+            before_ms: 10,
+            after_ms: 50,
+        };
+        // at 1kHz: keystrokes at 1s and 1.03s (merged), 2.4s and 2.44s (the second one touches speech),
+        // 3s (during speech) and 5s
+        let keystrokes = [1000, 1030, 2400, 2440, 3000, 5000].map(Duration::from_millis);
+        let speech = [(2480, 3500)];
+        let mut ducker = KeyboardDucker::new(1000, &keystrokes, &speech, &settings);
+
+        assert_eq!(ducker.windows, vec![990..1080, 2390..2450, 4990..5050]);
+        assert_eq!(ducker.gain(500), 1.0);
+        assert!((ducker.gain(1020) - 0.1).abs() < 1e-9);
+        assert_eq!(ducker.gain(3000), 1.0);
+        assert!((ducker.gain(5020) - 0.1).abs() < 1e-9);
+        assert_eq!(ducker.gain(6000), 1.0);
+    }
+}
@@ -0,0 +1,62 @@
+use std::{fs::File, io::BufWriter, path::PathBuf};
+
+use hound::WavReader;
+
+use super::{
+    audio_settings::{AudioFormat, EncodingSettings},
+    convert_audio::OUTPUT_SAMPLE_RATE,
+    flac::FlacWriter,
+    opus::OpusWriter,
+};
+
+/// Files of a recording, ready to be uploaded
+#[derive(Clone, Debug, Default)]
+pub struct EncodedAudio {
+    pub format: AudioFormat,
+    pub narration: PathBuf,
+    // where the frames of a flac narration start, its end last
+    pub frame_offsets: Vec<u64>,
+    // the system audio, when kept as its own track
+    pub system: Option<PathBuf>,
+    // what was captured, removed once the files are journaled
+    pub captures: Vec<PathBuf>,
+}
+
+/// Encodes a converted 16-bit recording in the upload format, next to it, and removes the wav.
+/// Returns the path of the file to upload, the wav itself when it is the upload format,
+/// with where its frames start when it is flac.
+pub fn encode_audio(
+    filepath: &PathBuf,
+    settings: &EncodingSettings,
+) -> Result<(PathBuf, Vec<u64>), Box<dyn std::error::Error + Send + Sync>> {
+    let encoded_path = filepath.with_extension(settings.format.extension());
+    let mut reader = WavReader::open(filepath)?;
+    let sample_rate = reader.spec().sample_rate;
+
+    let mut frame_offsets = Vec::new();
+    match settings.format {
+        AudioFormat::Wav => return Ok((filepath.clone(), frame_offsets)),
+        AudioFormat::Flac => {
+            let file = BufWriter::new(File::create(&encoded_path)?);
+            let mut writer = FlacWriter::new(file, sample_rate)?;
+            for sample in reader.samples::<i16>() {
+                writer.write_sample(sample?)?;
+            }
+            frame_offsets = writer.finalize()?.1;
+        }
+        AudioFormat::Opus => {
+            if sample_rate != OUTPUT_SAMPLE_RATE {
+                return Err(format!("Cannot encode {}Hz audio to opus", sample_rate).into());
+            }
+            let file = BufWriter::new(File::create(&encoded_path)?);
+            let mut writer = OpusWriter::new(file, settings.opus_bitrate_kbps)?;
+            for sample in reader.samples::<i16>() {
+                writer.write_sample(sample?)?;
+            }
+            writer.finalize()?;
+        }
+    }
+
+    std::fs::remove_file(filepath)?;
+    Ok((encoded_path, frame_offsets))
+}
@@ -0,0 +1,428 @@
+use std::io::{Seek, SeekFrom, Write};
+
+pub const BLOCK_SIZE: usize = 4096;
+const MAX_FIXED_ORDER: usize = 4;
+const MAX_PARTITION_ORDER: usize = 8;
+// rice parameters above this one would need the 5-bit rice coding method
+const MAX_RICE_PARAMETER: u32 = 14;
+const STREAMINFO_LENGTH: u32 = 34;
+
+/// Writes 16-bit mono audio as a FLAC stream, compressed with the fixed predictors
+/// and partitioned rice coding, which gets most of the gain of LPC on speech
+pub struct FlacWriter<W: Write + Seek> {
+    writer: W,
+    sample_rate: u32,
+    block: Vec<i32>,
+    frame_number: u64,
+    total_samples: u64,
+    min_frame_size: u32,
+    max_frame_size: u32,
+    // where each frame starts in the stream
+    frame_offsets: Vec<u64>,
+}
+
+impl<W: Write + Seek> FlacWriter<W> {
+    pub fn new(mut writer: W, sample_rate: u32) -> std::io::Result<FlacWriter<W>> {
+        writer.write_all(b"fLaC")?;
+        // the stream info is written again once the sizes are known
+        writer.write_all(&[0u8; 4 + STREAMINFO_LENGTH as usize])?;
+
+        Ok(FlacWriter {
+            writer,
+            sample_rate,
+            block: Vec::with_capacity(BLOCK_SIZE),
+            frame_number: 0,
+            total_samples: 0,
+            min_frame_size: u32::MAX,
+            max_frame_size: 0,
+            frame_offsets: vec![4 + 4 + STREAMINFO_LENGTH as u64],
+        })
+    }
+
+    pub fn write_sample(&mut self, sample: i16) -> std::io::Result<()> {
+        self.block.push(sample as i32);
+        if self.block.len() == BLOCK_SIZE {
+            self.write_frame()?;
+        }
+        Ok(())
+    }
+
+    /// Writes what is left and the stream info, returns the writer with where each frame starts,
+    /// the end of the stream last, so that a block of samples can be found by its bytes
+    pub fn finalize(mut self) -> std::io::Result<(W, Vec<u64>)> {
+        if !self.block.is_empty() {
+            self.write_frame()?;
+        }
+
+        self.writer.seek(SeekFrom::Start(4))?;
+        let stream_info = self.stream_info();
+        self.writer.write_all(&stream_info)?;
+        self.writer.seek(SeekFrom::End(0))?;
+        self.writer.flush()?;
+        Ok((self.writer, self.frame_offsets))
+    }
+
+    fn stream_info(&self) -> Vec<u8> {
+        let mut bits = BitWriter::default();
+        // last metadata block, of type STREAMINFO
+        bits.write(1, 1);
+        bits.write(0, 7);
+        bits.write(STREAMINFO_LENGTH as u64, 24);
+
+        bits.write(BLOCK_SIZE as u64, 16);
+        bits.write(BLOCK_SIZE as u64, 16);
+        let min_frame_size = if self.frame_number == 0 {
+            0
+        } else {
+            self.min_frame_size
+        };
+        bits.write(min_frame_size as u64, 24);
+        bits.write(self.max_frame_size as u64, 24);
+        bits.write(self.sample_rate as u64, 20);
+        bits.write(0, 3); // one channel
+        bits.write(15, 5); // 16 bits per sample
+        bits.write(self.total_samples, 36);
+        // no md5 signature, which the format allows
+        bits.write(0, 64);
+        bits.write(0, 64);
+        bits.into_bytes()
+    }
+
+    fn write_frame(&mut self) -> std::io::Result<()> {
+        let mut bits = BitWriter::default();
+        let block_size = self.block.len();
+
+        bits.write(0b11111111111110, 14);
+        bits.write(0, 1);
+        bits.write(0, 1); // fixed block size
+        let block_size_code = if block_size == BLOCK_SIZE {
+            0b1100
+        } else {
+            0b0111
+        };
+        bits.write(block_size_code, 4);
+        bits.write(sample_rate_code(self.sample_rate), 4);
+        bits.write(0, 4); // mono
+        bits.write(0b100, 3); // 16 bits per sample
+        bits.write(0, 1);
+        bits.write_utf8(self.frame_number);
+        if block_size_code == 0b0111 {
+            bits.write(block_size as u64 - 1, 16);
+        }
+        if sample_rate_code(self.sample_rate) == 0b1101 {
+            bits.write(self.sample_rate as u64, 16);
+        }
+        let header_crc = crc8(bits.bytes());
+        bits.write(header_crc as u64, 8);
+
+        write_subframe(&mut bits, &self.block);
+        bits.align();
+        let frame_crc = crc16(bits.bytes());
+        bits.write(frame_crc as u64, 16);
+
+        let frame = bits.into_bytes();
+        self.writer.write_all(&frame)?;
+        self.min_frame_size = self.min_frame_size.min(frame.len() as u32);
+        self.max_frame_size = self.max_frame_size.max(frame.len() as u32);
+        let frame_end = self.frame_offsets.last().copied().unwrap_or_default() + frame.len() as u64;
+        self.frame_offsets.push(frame_end);
+        self.total_samples += block_size as u64;
+        self.frame_number += 1;
+        self.block.clear();
+        Ok(())
+    }
+}
+
+fn sample_rate_code(sample_rate: u32) -> u64 {
+    match sample_rate {
+        8000 => 0b0100,
+        16000 => 0b0101,
+        22050 => 0b0110,
+        24000 => 0b0111,
+        32000 => 0b1000,
+        44100 => 0b1001,
+        48000 => 0b1010,
+        96000 => 0b1011,
+        // rate given in Hz after the header
+        _ => 0b1101,
+    }
+}
+
+fn write_subframe(bits: &mut BitWriter, block: &[i32]) {
+    if block.iter().all(|sample| *sample == block[0]) {
+        bits.write(0, 1);
+        bits.write(0b000000, 6); // constant
+        bits.write(0, 1);
+        bits.write(block[0] as u16 as u64, 16);
+        return;
+    }
+
+    // the fixed predictor of the order that leaves the cheapest residual
+    let (order, residual, rice) = (0..=MAX_FIXED_ORDER.min(block.len() - 1))
+        .map(|order| {
+            let residual = fixed_residual(block, order);
+            let rice = RicePartitions::best(&residual, block.len(), order);
+            (order, residual, rice)
+        })
+        .min_by_key(|(_, _, rice)| rice.bits)
+        .unwrap();
+
+    // 6 bits of header, 16 per warm-up sample, 6 of rice coding header
+    if rice.bits + 16 * order as u64 + 6 >= 16 * block.len() as u64 {
+        bits.write(0, 1);
+        bits.write(0b000001, 6); // verbatim
+        bits.write(0, 1);
+        for sample in block {
+            bits.write(*sample as u16 as u64, 16);
+        }
+        return;
+    }
+
+    bits.write(0, 1);
+    bits.write(0b001000 | order as u64, 6);
+    bits.write(0, 1);
+    for sample in &block[..order] {
+        bits.write(*sample as u16 as u64, 16);
+    }
+
+    bits.write(0b00, 2); // rice coding with 4-bit parameters
+    bits.write(rice.order as u64, 4);
+    let partition_size = block.len() >> rice.order;
+    let mut start = 0;
+    for (partition, parameter) in rice.parameters.iter().enumerate() {
+        let end = (partition + 1) * partition_size;
+        bits.write(*parameter as u64, 4);
+        for value in &residual[start..end - order] {
+            bits.write_rice(zigzag(*value), *parameter);
+        }
+        start = end - order;
+    }
+}
+
+/// Differences of the samples with their polynomial prediction from the previous ones
+fn fixed_residual(block: &[i32], order: usize) -> Vec<i64> {
+    (order..block.len())
+        .map(|i| {
+            let s = |offset: usize| block[i - offset] as i64;
+            match order {
+                0 => s(0),
+                1 => s(0) - s(1),
+                2 => s(0) - 2 * s(1) + s(2),
+                3 => s(0) - 3 * s(1) + 3 * s(2) - s(3),
+                _ => s(0) - 4 * s(1) + 6 * s(2) - 4 * s(3) + s(4),
+            }
+        })
+        .collect()
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+struct RicePartitions {
+    order: usize,
+    parameters: Vec<u32>,
+    bits: u64,
+}
+
+impl RicePartitions {
+    /// Partition order and per partition parameters that code the residual in the fewest bits.
+    /// The first partition is shorter by the warm-up samples of the predictor.
+    fn best(residual: &[i64], block_size: usize, predictor_order: usize) -> RicePartitions {
+        let values: Vec<u64> = residual.iter().map(|value| zigzag(*value)).collect();
+
+        let mut best: Option<RicePartitions> = None;
+        for order in 0..=MAX_PARTITION_ORDER {
+            let partition_size = block_size >> order;
+            if !block_size.is_multiple_of(1 << order) || partition_size <= predictor_order {
+                break;
+            }
+
+            let mut parameters = Vec::with_capacity(1 << order);
+            let mut bits = 6;
+            let mut start = 0;
+            for partition in 0..(1 << order) {
+                let end = (partition + 1) * partition_size - predictor_order;
+                let (parameter, partition_bits) = best_parameter(&values[start..end]);
+                parameters.push(parameter);
+                bits += 4 + partition_bits;
+                start = end;
+            }
+
+            if best.as_ref().is_none_or(|best| bits < best.bits) {
+                best = Some(RicePartitions {
+                    order,
+                    parameters,
+                    bits,
+                });
+            }
+        }
+        best.unwrap()
+    }
+}
+
+fn best_parameter(values: &[u64]) -> (u32, u64) {
+    (0..=MAX_RICE_PARAMETER)
+        .map(|parameter| {
+            let bits = values
+                .iter()
+                .map(|value| (value >> parameter) + 1 + parameter as u64)
+                .sum::<u64>();
+            (parameter, bits)
+        })
+        .min_by_key(|(_, bits)| *bits)
+        .unwrap()
+}
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    accumulator: u64,
+    length: u32,
+}
+
+impl BitWriter {
+    /// Writes the `count` lowest bits of `value`, most significant first
+    fn write(&mut self, value: u64, count: u32) {
+        for shift in (0..count).rev() {
+            self.accumulator = (self.accumulator << 1) | ((value >> shift) & 1);
+            self.length += 1;
+            if self.length == 8 {
+                self.bytes.push(self.accumulator as u8);
+                self.accumulator = 0;
+                self.length = 0;
+            }
+        }
+    }
+
+    fn write_rice(&mut self, value: u64, parameter: u32) {
+        for _ in 0..value >> parameter {
+            self.write(0, 1);
+        }
+        self.write(1, 1);
+        self.write(value, parameter);
+    }
+
+    /// Frame number in the extended utf-8 coding of the format
+    fn write_utf8(&mut self, value: u64) {
+        if value < 0x80 {
+            self.write(value, 8);
+            return;
+        }
+        let mut continuation = 1;
+        while value >= 1 << (5 * continuation + 6) {
+            continuation += 1;
+        }
+        let marker = (0xff00u64 >> (continuation + 1)) & 0xff;
+        self.write(marker | (value >> (6 * continuation)), 8);
+        for i in (0..continuation).rev() {
+            self.write(0b10, 2);
+            self.write(value >> (6 * i), 6);
+        }
+    }
+
+    fn align(&mut self) {
+        if self.length > 0 {
+            self.write(0, 8 - self.length);
+        }
+    }
+
+    /// Complete bytes written so far
+    fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn into_bytes(mut self) -> Vec<u8> {
+        self.align();
+        self.bytes
+    }
+}
+
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |crc, byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |crc, byte| {
+        (0..8).fold(crc ^ ((*byte as u16) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn compresses_speech_like_signal() {
+        let mut writer = FlacWriter::new(Cursor::new(Vec::new()), 16000).unwrap();
+        let length = 16000 * 2 + 123;
+        for i in 0..length {
+            let t = i as f64 / 16000.0;
+            let sample = 8000.0 * (t * 220.0 * std::f64::consts::TAU).sin()
+                + 2000.0 * (t * 1330.0 * std::f64::consts::TAU).sin();
+            writer.write_sample(sample as i16).unwrap();
+        }
+        let flac = writer.finalize().unwrap().0.into_inner();
+
+        assert_eq!(&flac[..4], b"fLaC");
+        // total samples, in the last 36 bits before the md5 signature
+        let info = &flac[8..42];
+        let total = u64::from_be_bytes(info[10..18].try_into().unwrap()) & 0xf_ffff_ffff;
+        assert_eq!(total, length as u64);
+        assert!(flac.len() < length * 2 * 3 / 4, "{} bytes", flac.len());
+    }
+
+    #[test]
+    fn decodes_to_the_written_samples() {
+        // speech-like audio, a constant block, noise over the whole range and a partial last block
+        let mut seed = 1u32;
+        let mut noise = || {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            (seed >> 16) as i16
+        };
+        let mut samples: Vec<i16> = (0..BLOCK_SIZE * 2)
+            .map(|i| {
+                let t = i as f64 / 16000.0;
+                (9000.0 * (t * 180.0 * std::f64::consts::TAU).sin()) as i16 + noise() / 100
+            })
+            .collect();
+        samples.extend(std::iter::repeat_n(42, BLOCK_SIZE));
+        samples.extend((0..BLOCK_SIZE).map(|_| noise()));
+        samples.extend([i16::MIN, i16::MAX].repeat(BLOCK_SIZE / 2));
+        samples.extend((0..777).map(|i| [i16::MAX, 0, i16::MIN][i % 3]));
+
+        let mut writer = FlacWriter::new(Cursor::new(Vec::new()), 16000).unwrap();
+        for sample in &samples {
+            writer.write_sample(*sample).unwrap();
+        }
+        let flac = writer.finalize().unwrap().0.into_inner();
+
+        let mut reader = claxon::FlacReader::new(Cursor::new(flac)).unwrap();
+        let info = reader.streaminfo();
+        assert_eq!(info.samples, Some(samples.len() as u64));
+        assert_eq!((info.sample_rate, info.channels), (16000, 1));
+        let decoded: Vec<i16> = reader
+            .samples()
+            .map(|sample| sample.unwrap() as i16)
+            .collect();
+        assert_eq!(decoded.len(), samples.len());
+        let mismatch = decoded.iter().zip(&samples).position(|(a, b)| a != b);
+        assert_eq!(mismatch, None);
+    }
+}
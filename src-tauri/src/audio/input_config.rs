@@ -0,0 +1,82 @@
+use cpal::{traits::DeviceTrait, SampleRate, SupportedStreamConfig, SupportedStreamConfigRange};
+
+use super::audio_settings::InputConfig;
+
+// offered within the ranges of devices that take any rate, next to the ends of the ranges
+const COMMON_SAMPLE_RATES: [u32; 10] = [
+    8000, 11025, 16000, 22050, 32000, 44100, 48000, 88200, 96000, 192000,
+];
+
+fn supports_rate(range: &SupportedStreamConfigRange, sample_rate: u32) -> bool {
+    (range.min_sample_rate().0..=range.max_sample_rate().0).contains(&sample_rate)
+}
+
+/// The configs a device can be opened with, whatever their sample format
+pub fn supported_input_configs(
+    device: &cpal::Device,
+) -> Result<Vec<InputConfig>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut configs: Vec<InputConfig> = device
+        .supported_input_configs()?
+        .flat_map(|range| {
+            let rates = [range.min_sample_rate().0, range.max_sample_rate().0];
+            rates
+                .into_iter()
+                .chain(COMMON_SAMPLE_RATES)
+                .filter(|sample_rate| supports_rate(&range, *sample_rate))
+                .map(|sample_rate| InputConfig {
+                    sample_rate,
+                    channels: range.channels(),
+                })
+                .collect::<Vec<InputConfig>>()
+        })
+        .collect();
+
+    configs.sort();
+    configs.dedup();
+    Ok(configs)
+}
+
+pub fn default_input_config(
+    device: &cpal::Device,
+) -> Result<InputConfig, Box<dyn std::error::Error + Send + Sync>> {
+    let config = device.default_input_config()?;
+    Ok(InputConfig {
+        sample_rate: config.sample_rate().0,
+        channels: config.channels(),
+    })
+}
+
+/// The stream config of the device for the chosen config, its default one when none was chosen
+/// or when the device does not support the chosen one anymore
+pub fn input_stream_config(
+    device: &cpal::Device,
+    chosen: Option<&InputConfig>,
+) -> Result<SupportedStreamConfig, Box<dyn std::error::Error + Send + Sync>> {
+    let default_config = device.default_input_config()?;
+    let Some(chosen) = chosen else {
+        return Ok(default_config);
+    };
+
+    let ranges: Vec<SupportedStreamConfigRange> = device
+        .supported_input_configs()?
+        .filter(|range| {
+            range.channels() == chosen.channels && supports_rate(range, chosen.sample_rate)
+        })
+        .collect();
+    // the default sample format is kept when the device offers it with the chosen config
+    let range = ranges
+        .iter()
+        .find(|range| range.sample_format() == default_config.sample_format())
+        .or(ranges.first());
+
+    match range {
+        Some(range) => Ok((*range).with_sample_rate(SampleRate(chosen.sample_rate))),
+        None => {
+            eprintln!(
+                "Input config {:?} is not supported anymore, using the default one",
+                chosen
+            );
+            Ok(default_config)
+        }
+    }
+}
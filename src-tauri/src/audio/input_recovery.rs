@@ -0,0 +1,288 @@
+use std::{
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use cpal::{
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+    SupportedStreamConfig, SupportedStreamConfigRange,
+};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::mpsc::{error::TryRecvError, Receiver};
+
+use super::{
+    audio_controller::AudioCommand,
+    capture::{build_capture_stream, CaptureInput, CaptureWriter},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// some backends never report a device that went away, its stream just stops delivering
+const STALL_TIMEOUT: Duration = Duration::from_secs(2);
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum AudioDeviceLostReason {
+    Disconnected, // The device reported that it is not available anymore
+    Stalled,      // The device stopped delivering frames
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AudioDeviceLostEvent {
+    pub device: String,
+    pub reason: AudioDeviceLostReason,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AudioDeviceRestoredEvent {
+    pub device: String,
+    pub fallback: bool, // the system default input took over from the lost device
+}
+
+/// The first of the devices that can capture at the sample rate, with the config it would capture with.
+/// The capture file keeps the sample rate it was created with, only the converted audio is resampled,
+/// so a device that cannot capture at it is passed over and the recording waits for one that can.
+fn first_at_sample_rate<D>(
+    devices: impl IntoIterator<Item = (D, Vec<SupportedStreamConfigRange>)>,
+    sample_rate: u32,
+) -> Option<(D, SupportedStreamConfig)> {
+    devices.into_iter().find_map(|(device, configs)| {
+        let config = configs
+            .into_iter()
+            .find(|config| {
+                (config.min_sample_rate().0..=config.max_sample_rate().0).contains(&sample_rate)
+            })?
+            .with_sample_rate(cpal::SampleRate(sample_rate));
+        Some((device, config))
+    })
+}
+
+/// The lost device if it is back, the default input otherwise, at the sample rate of the recording
+fn replacement_device(
+    device_name: &str,
+    sample_rate: u32,
+) -> Option<(cpal::Device, SupportedStreamConfig)> {
+    let host = cpal::default_host();
+    let lost_device = host.input_devices().ok().and_then(|mut devices| {
+        devices.find(|device| device.name().is_ok_and(|name| name == device_name))
+    });
+
+    let devices = lost_device
+        .into_iter()
+        .chain(host.default_input_device())
+        .filter_map(|device| {
+            let configs = device.supported_input_configs().ok()?.collect();
+            Some((device, configs))
+        });
+    first_at_sample_rate(devices, sample_rate)
+}
+
+/// The microphone stream of a recording, moved to another device when its own one is lost
+pub struct RecoverableInput {
+    device_name: String,
+    sample_rate: u32,
+    stream: Option<cpal::Stream>,
+    errors: mpsc::Receiver<cpal::StreamError>,
+    error_sender: mpsc::Sender<cpal::StreamError>,
+    captured_frames: u64,
+    progress_time: Instant,
+    retry_time: Instant,
+    // nothing is captured during a pause, which is not a stall
+    paused: bool,
+}
+
+impl RecoverableInput {
+    pub fn start(
+        device: &cpal::Device,
+        config: SupportedStreamConfig,
+        input: CaptureInput,
+    ) -> Result<RecoverableInput, Box<dyn std::error::Error + Send + Sync>> {
+        let (error_sender, errors) = mpsc::channel();
+        let sample_rate = config.sample_rate().0;
+        let stream = build_capture_stream(device, config, input, forward_errors(&error_sender))?;
+        stream.play()?;
+
+        Ok(RecoverableInput {
+            // only told to the frontend and used to prefer the same device again
+            device_name: device.name().unwrap_or_default(),
+            sample_rate,
+            stream: Some(stream),
+            errors,
+            error_sender,
+            captured_frames: 0,
+            progress_time: Instant::now(),
+            retry_time: Instant::now(),
+            paused: false,
+        })
+    }
+
+    /// Watches the input until a command other than start comes, blocking the calling thread.
+    /// The time without a device is written as silence, and reported as a gap of the capture.
+    pub fn watch(
+        &mut self,
+        app_handle: &AppHandle,
+        capture_writer: &CaptureWriter,
+        receiver: &mut Receiver<AudioCommand>,
+    ) -> AudioCommand {
+        loop {
+            match receiver.try_recv() {
+                Ok(AudioCommand::Start) | Err(TryRecvError::Empty) => {}
+                Ok(command) => return command,
+                Err(TryRecvError::Disconnected) => return AudioCommand::Stop,
+            }
+
+            if self.stream.is_some() {
+                self.check(app_handle, capture_writer);
+            } else if self.retry_time <= Instant::now() {
+                self.retry(app_handle, capture_writer);
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    pub fn set_paused(&mut self, capture_writer: &CaptureWriter, paused: bool) {
+        capture_writer.set_paused(paused);
+        self.paused = paused;
+        self.progress_time = Instant::now();
+    }
+
+    fn check(&mut self, app_handle: &AppHandle, capture_writer: &CaptureWriter) {
+        let disconnected = self
+            .errors
+            .try_iter()
+            .any(|err| matches!(err, cpal::StreamError::DeviceNotAvailable));
+
+        let captured_frames = capture_writer.captured_frames();
+        if captured_frames > self.captured_frames {
+            self.captured_frames = captured_frames;
+            self.progress_time = Instant::now();
+        }
+
+        let reason = if disconnected {
+            AudioDeviceLostReason::Disconnected
+        } else if !self.paused && self.progress_time.elapsed() > STALL_TIMEOUT {
+            AudioDeviceLostReason::Stalled
+        } else {
+            return;
+        };
+
+        // the writer has to know before the input is dropped, or it would take it for the end
+        capture_writer.mark_input_lost();
+        self.stream = None;
+        self.retry_time = Instant::now();
+
+        // the capture goes on without the frontend knowing
+        if let Err(e) = app_handle.emit(
+            "audio-device-lost",
+            AudioDeviceLostEvent {
+                device: self.device_name.clone(),
+                reason,
+            },
+        ) {
+            eprintln!("Failed to emit audio-device-lost: {}", e);
+        }
+    }
+
+    fn retry(&mut self, app_handle: &AppHandle, capture_writer: &CaptureWriter) {
+        self.retry_time = Instant::now() + RETRY_INTERVAL;
+        let Some((device, config)) = replacement_device(&self.device_name, self.sample_rate) else {
+            return;
+        };
+
+        // the errors of the lost stream are not about the next one
+        self.errors.try_iter().for_each(drop);
+
+        let (input, source) = capture_writer.new_input(config.channels());
+        let stream = match build_capture_stream(
+            &device,
+            config,
+            input,
+            forward_errors(&self.error_sender),
+        ) {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Failed to open a replacement audio device: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = stream.play() {
+            eprintln!("Failed to start a replacement audio device: {}", e);
+            return;
+        }
+        capture_writer.replace_input(source);
+
+        let device_name = device.name().unwrap_or_default();
+        self.stream = Some(stream);
+        self.progress_time = Instant::now();
+
+        if let Err(e) = app_handle.emit(
+            "audio-device-restored",
+            AudioDeviceRestoredEvent {
+                fallback: device_name != self.device_name,
+                device: device_name,
+            },
+        ) {
+            eprintln!("Failed to emit audio-device-restored: {}", e);
+        }
+    }
+}
+
+fn forward_errors(
+    error_sender: &mpsc::Sender<cpal::StreamError>,
+) -> impl FnMut(cpal::StreamError) + Send + 'static {
+    let error_sender = error_sender.clone();
+    move |err| {
+        eprintln!("an error occurred on stream: {}", err);
+        let _ = error_sender.send(err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cpal::{SampleFormat, SampleRate, SupportedBufferSize};
+
+    use super::*;
+
+    fn configs(rates: &[(u32, u32)]) -> Vec<SupportedStreamConfigRange> {
+        rates
+            .iter()
+            .map(|(min, max)| {
+                SupportedStreamConfigRange::new(
+                    1,
+                    SampleRate(*min),
+                    SampleRate(*max),
+                    SupportedBufferSize::Unknown,
+                    SampleFormat::F32,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lost_device_is_preferred_at_the_sample_rate() {
+        let devices = || {
+            vec![
+                ("lost", configs(&[(44100, 44100), (8000, 48000)])),
+                ("default", configs(&[(48000, 48000)])),
+            ]
+        };
+        let (device, config) = first_at_sample_rate(devices(), 48000).unwrap();
+        assert_eq!((device, config.sample_rate().0), ("lost", 48000));
+        let (device, config) = first_at_sample_rate(devices(), 16000).unwrap();
+        assert_eq!((device, config.sample_rate().0), ("lost", 16000));
+    }
+
+    #[test]
+    fn device_without_the_sample_rate_is_passed_over() {
+        let devices = vec![
+            ("lost", configs(&[(44100, 44100)])),
+            ("default", configs(&[(16000, 96000)])),
+        ];
+        let (device, config) = first_at_sample_rate(devices, 48000).unwrap();
+        assert_eq!((device, config.sample_rate().0), ("default", 48000));
+
+        let devices = vec![("default", configs(&[(44100, 44100)]))];
+        assert!(first_at_sample_rate(devices, 48000).is_none());
+    }
+}
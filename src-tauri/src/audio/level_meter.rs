@@ -0,0 +1,164 @@
+use serde::Serialize;
+
+use super::{audio_settings::LevelMonitorSettings, loudness::gain_to_db};
+
+// one level event per window, so that the frontend is not flooded
+const WINDOW_MS: u64 = 100;
+// samples this close to full scale are counted as clipped
+const CLIPPING_LEVEL: f32 = 0.999;
+// reported for digital silence instead of minus infinity
+const FLOOR_DB: f64 = -100.0;
+
+/// Level of the input over the last window, in dBFS
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioLevel {
+    pub rms_db: f64,
+    pub peak_db: f64,
+    pub clipped: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioWarningKind {
+    Silence,
+    Clipping,
+}
+
+/// Sent when the input starts or stops being silent or clipping for too long
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioWarning {
+    pub kind: AudioWarningKind,
+    pub active: bool,
+    pub duration_ms: u64,
+}
+
+/// Time a condition has held for, and whether it was warned about
+#[derive(Default)]
+struct Condition {
+    duration_ms: u64,
+    warned: bool,
+}
+
+impl Condition {
+    fn update(
+        &mut self,
+        kind: AudioWarningKind,
+        holds: bool,
+        limit_ms: u64,
+    ) -> Option<AudioWarning> {
+        if holds {
+            self.duration_ms += WINDOW_MS;
+            if self.duration_ms >= limit_ms && !self.warned {
+                self.warned = true;
+                return Some(AudioWarning {
+                    kind,
+                    active: true,
+                    duration_ms: self.duration_ms,
+                });
+            }
+            return None;
+        }
+
+        let warning = self.warned.then_some(AudioWarning {
+            kind,
+            active: false,
+            duration_ms: self.duration_ms,
+        });
+        *self = Condition::default();
+        warning
+    }
+}
+
+/// Measures the captured samples by windows, on the writer thread
+pub struct LevelMeter {
+    settings: LevelMonitorSettings,
+    window_samples: usize,
+    count: usize,
+    sum_squares: f64,
+    peak: f32,
+    silence: Condition,
+    clipping: Condition,
+}
+
+impl LevelMeter {
+    pub fn new(sample_rate: u32, channels: u16, settings: &LevelMonitorSettings) -> LevelMeter {
+        LevelMeter {
+            settings: settings.clone(),
+            window_samples: (sample_rate as u64 * channels.max(1) as u64 * WINDOW_MS / 1000).max(1)
+                as usize,
+            count: 0,
+            sum_squares: 0.0,
+            peak: 0.0,
+            silence: Condition::default(),
+            clipping: Condition::default(),
+        }
+    }
+
+    /// Returns the level once a window is complete, with the warnings that changed
+    pub fn push(&mut self, sample: f32) -> Option<(AudioLevel, Vec<AudioWarning>)> {
+        self.sum_squares += sample as f64 * sample as f64;
+        self.peak = self.peak.max(sample.abs());
+        self.count += 1;
+        if self.count < self.window_samples {
+            return None;
+        }
+
+        let level = AudioLevel {
+            rms_db: gain_to_db((self.sum_squares / self.count as f64).sqrt()).max(FLOOR_DB),
+            peak_db: gain_to_db(self.peak as f64).max(FLOOR_DB),
+            clipped: self.peak >= CLIPPING_LEVEL,
+        };
+        self.count = 0;
+        self.sum_squares = 0.0;
+        self.peak = 0.0;
+
+        let warnings = [
+            self.silence.update(
+                AudioWarningKind::Silence,
+                level.rms_db < self.settings.silence_threshold_db,
+                self.settings.silence_warning_ms,
+            ),
+            self.clipping.update(
+                AudioWarningKind::Clipping,
+                level.clipped,
+                self.settings.clipping_warning_ms,
+            ),
+        ];
+        Some((level, warnings.into_iter().flatten().collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warns_once_about_long_silence() {
+        let settings = LevelMonitorSettings {
+            silence_threshold_db: -60.0,
+            silence_warning_ms: 1000,
+            clipping_warning_ms: 200,
+        };
+        let mut meter = LevelMeter::new(1000, 1, &settings);
+        let mut feed = |sample: f32, samples: usize| -> Vec<AudioWarning> {
+            (0..samples)
+                .filter_map(|_| meter.push(sample))
+                .flat_map(|(_, warnings)| warnings)
+                .collect()
+        };
+
+        assert_eq!(feed(0.1, 500), vec![]);
+        let warnings = feed(0.0, 3000);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, AudioWarningKind::Silence);
+        assert_eq!(warnings[0].duration_ms, 1000);
+
+        let warnings = feed(1.0, 300);
+        assert_eq!(warnings.len(), 2);
+        assert!(!warnings[0].active);
+        assert_eq!(warnings[0].duration_ms, 3000);
+        assert_eq!(warnings[1].kind, AudioWarningKind::Clipping);
+    }
+}
@@ -0,0 +1,165 @@
+use std::{collections::VecDeque, f64::consts::PI};
+
+// the true peak is estimated by interpolating 4 positions between samples
+const OVERSAMPLING: usize = 4;
+const INTERPOLATION_TAPS: usize = 12;
+// time given to the gain to go down before a peak, and to recover after it
+const LOOKAHEAD_SECONDS: f64 = 0.005;
+const RELEASE_SECONDS: f64 = 0.1;
+
+/// Estimates the true (inter-sample) peak of a signal, as recommended by ITU-R BS.1770
+pub struct TruePeakMeter {
+    phases: Vec<[f64; INTERPOLATION_TAPS]>,
+    history: VecDeque<f64>,
+}
+
+impl TruePeakMeter {
+    pub fn new() -> TruePeakMeter {
+        // Hann-windowed sinc interpolation filter split in polyphase components
+        let half = INTERPOLATION_TAPS as f64 / 2.0;
+        let phases = (0..OVERSAMPLING)
+            .map(|phase| {
+                let mut taps = [0.0; INTERPOLATION_TAPS];
+                for (tap, coefficient) in taps.iter_mut().enumerate() {
+                    let distance = tap as f64 - half + 1.0 - phase as f64 / OVERSAMPLING as f64;
+                    let window = 0.5 + 0.5 * (PI * distance / (half + 1.0)).cos();
+                    *coefficient = sinc(distance) * window;
+                }
+                taps
+            })
+            .collect();
+
+        TruePeakMeter {
+            phases,
+            history: VecDeque::from(vec![0.0; INTERPOLATION_TAPS]),
+        }
+    }
+
+    /// Peak around the sample pushed `delay()` samples ago
+    pub fn push(&mut self, sample: f64) -> f64 {
+        self.history.pop_front();
+        self.history.push_back(sample);
+
+        self.phases
+            .iter()
+            .map(|taps| {
+                taps.iter()
+                    .zip(self.history.iter())
+                    .map(|(coefficient, sample)| coefficient * sample)
+                    .sum::<f64>()
+                    .abs()
+            })
+            .fold(0.0, f64::max)
+    }
+
+    pub fn delay() -> usize {
+        INTERPOLATION_TAPS / 2
+    }
+}
+
+impl Default for TruePeakMeter {
+    fn default() -> TruePeakMeter {
+        TruePeakMeter::new()
+    }
+}
+
+/// Look-ahead limiter keeping the estimated true peak of the signal under a ceiling.
+/// The output is delayed by `lookahead + interpolation delay` samples.
+pub struct Limiter {
+    ceiling: f64,
+    meter: TruePeakMeter,
+    delay: VecDeque<f64>,
+    required_gains: VecDeque<f64>,
+    lookahead: usize,
+    release: f64,
+    gain: f64,
+}
+
+impl Limiter {
+    pub fn new(sample_rate: u32, ceiling: f64) -> Limiter {
+        let lookahead = (sample_rate as f64 * LOOKAHEAD_SECONDS).ceil() as usize;
+        Limiter {
+            ceiling,
+            meter: TruePeakMeter::new(),
+            delay: VecDeque::with_capacity(lookahead + TruePeakMeter::delay() + 1),
+            required_gains: VecDeque::from(vec![1.0; lookahead + 1]),
+            lookahead,
+            release: 1.0 - (-1.0 / (sample_rate as f64 * RELEASE_SECONDS)).exp(),
+            gain: 1.0,
+        }
+    }
+
+    pub fn process(&mut self, sample: f64, output: &mut Vec<f64>) {
+        let peak = self.meter.push(sample);
+        self.required_gains.pop_front();
+        self.required_gains
+            .push_back(if peak > self.ceiling { self.ceiling / peak } else { 1.0 });
+
+        // the gain goes down at once to what any peak in the look-ahead window needs,
+        // then recovers smoothly
+        let target = self.required_gains.iter().cloned().fold(1.0, f64::min);
+        self.gain = if target < self.gain {
+            target
+        } else {
+            self.gain + (target - self.gain) * self.release
+        };
+
+        self.delay.push_back(sample);
+        if self.delay.len() > self.lookahead + TruePeakMeter::delay() {
+            if let Some(delayed) = self.delay.pop_front() {
+                output.push(delayed * self.gain);
+            }
+        }
+    }
+
+    /// Outputs the samples still held in the look-ahead window, the gain goes on with what follows
+    pub fn drain(&mut self, output: &mut Vec<f64>) {
+        for _ in 0..self.lookahead + TruePeakMeter::delay() {
+            self.process(0.0, output);
+        }
+        // the silence pushed to drain the window is not part of the signal
+        self.delay.clear();
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_true_peak_under_ceiling() {
+        let ceiling = 10.0_f64.powf(-1.0 / 20.0);
+        // a tone near nyquist has inter-sample peaks above its sample values
+        let input: Vec<f64> = (0..16000)
+            .map(|i| 1.4 * (2.0 * PI * 7000.0 * i as f64 / 16000.0 + 0.3).sin())
+            .collect();
+
+        let mut limiter = Limiter::new(16000, ceiling);
+        let mut output = Vec::new();
+        input.iter().for_each(|sample| limiter.process(*sample, &mut output));
+        limiter.drain(&mut output);
+        assert_eq!(output.len(), input.len());
+
+        let mut meter = TruePeakMeter::new();
+        let true_peak = output.iter().map(|sample| meter.push(*sample)).fold(0.0, f64::max);
+        assert!(true_peak < ceiling * 1.02, "true peak {}", true_peak);
+    }
+
+    #[test]
+    fn leaves_quiet_signal_untouched() {
+        let input: Vec<f64> = (0..1600).map(|i| 0.3 * (i as f64 * 0.1).sin()).collect();
+        let mut limiter = Limiter::new(16000, 0.9);
+        let mut output = Vec::new();
+        input.iter().for_each(|sample| limiter.process(*sample, &mut output));
+        limiter.drain(&mut output);
+        assert_eq!(output, input);
+    }
+}
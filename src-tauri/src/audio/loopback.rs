@@ -0,0 +1,90 @@
+use cpal::{
+    traits::{DeviceTrait, HostTrait},
+    DefaultStreamConfigError, SupportedStreamConfig,
+};
+
+// virtual devices usually installed on macos to capture the system output
+#[cfg(target_os = "macos")]
+const LOOPBACK_DRIVERS: [&str; 3] = ["blackhole", "loopback", "soundflower"];
+
+// cpal only sees alsa devices on linux, the sound servers show theirs as one device each,
+// which captures the monitor of the default output when the session asks for it
+#[cfg(target_os = "linux")]
+const SOUND_SERVER_DEVICES: [&str; 2] = ["pipewire", "pulse"];
+#[cfg(target_os = "linux")]
+const MONITOR_SOURCE: &str = "@DEFAULT_MONITOR@";
+
+/// Sources that capture what the system plays:
+/// on windows the output devices, opened in WASAPI loopback mode,
+/// on linux the alsa monitor devices and the PulseAudio / PipeWire devices, opened on the default monitor,
+/// on macos the virtual loopback devices, as the system offers none
+pub fn loopback_devices() -> Result<Vec<cpal::Device>, String> {
+    let host = cpal::default_host();
+
+    #[cfg(target_os = "windows")]
+    let devices = host
+        .output_devices()
+        .map_err(|e| e.to_string())?
+        .collect::<Vec<cpal::Device>>();
+
+    #[cfg(not(target_os = "windows"))]
+    let devices = host
+        .input_devices()
+        .map_err(|e| e.to_string())?
+        .filter(|device| {
+            let name = device.name().unwrap_or_default().to_lowercase();
+            #[cfg(target_os = "macos")]
+            return LOOPBACK_DRIVERS.iter().any(|driver| name.contains(driver));
+            #[cfg(not(target_os = "macos"))]
+            return name.contains("monitor") || SOUND_SERVER_DEVICES.contains(&name.as_str());
+        })
+        .collect::<Vec<cpal::Device>>();
+
+    Ok(devices
+        .into_iter()
+        .filter(|device| loopback_config(device).is_ok())
+        .collect())
+}
+
+/// The named loopback source, or the first one available
+pub fn find_loopback_device(name: Option<&str>) -> Result<cpal::Device, String> {
+    loopback_devices()?
+        .into_iter()
+        .find(|device| {
+            name.is_none_or(|name| device.name().is_ok_and(|device_name| device_name == name))
+        })
+        .ok_or_else(|| format!("Loopback device '{}' not found", name.unwrap_or("default")))
+}
+
+/// Stream config to open the loopback source with, as an input stream
+pub fn loopback_config(
+    device: &cpal::Device,
+) -> Result<SupportedStreamConfig, DefaultStreamConfigError> {
+    #[cfg(target_os = "windows")]
+    return device.default_output_config();
+    #[cfg(not(target_os = "windows"))]
+    return device.default_input_config();
+}
+
+/// Opens the stream of a loopback source with `open`. The sound servers on linux read the source
+/// a stream records from when it is opened, it is set to their default monitor just for that.
+pub fn open_loopback<T>(device: &cpal::Device, open: impl FnOnce() -> T) -> T {
+    #[cfg(target_os = "linux")]
+    {
+        let name = device.name().unwrap_or_default().to_lowercase();
+        if SOUND_SERVER_DEVICES.contains(&name.as_str()) {
+            let source = std::env::var_os("PULSE_SOURCE");
+            std::env::set_var("PULSE_SOURCE", MONITOR_SOURCE);
+            let opened = open();
+            // the microphone, and its replacements, are opened on the source the session chose
+            match source {
+                Some(source) => std::env::set_var("PULSE_SOURCE", source),
+                None => std::env::remove_var("PULSE_SOURCE"),
+            }
+            return opened;
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = device;
+    open()
+}
@@ -0,0 +1,153 @@
+use std::f64::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+use super::biquad::Biquad;
+
+// blocks of 400ms overlapping by 75%, measured every 100ms
+const BLOCK_STEPS: usize = 4;
+const STEP_SECONDS: f64 = 0.1;
+const ABSOLUTE_GATE: f64 = -70.0;
+const RELATIVE_GATE: f64 = -10.0;
+
+/// Result of the loudness normalization, reported in the run-tutorial metadata
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoudnessReport {
+    // None when the recording is silent
+    pub integrated_loudness: Option<f64>,
+    pub target_loudness: f64,
+    pub gain_db: f64,
+    pub true_peak_dbtp: f64,
+}
+
+/// Integrated loudness of a mono signal as specified by ITU-R BS.1770 / EBU R128:
+/// K-weighting, mean square over 400ms blocks, then absolute and relative gating.
+/// Only the energy of every 100ms step is kept, so long recordings stay cheap.
+pub struct LoudnessMeter {
+    shelf: Biquad,
+    high_pass: Biquad,
+    step_length: usize,
+    step_energy: f64,
+    step_count: usize,
+    steps: Vec<f64>,
+}
+
+impl LoudnessMeter {
+    pub fn new(sample_rate: u32) -> LoudnessMeter {
+        // filter coefficients derived for any sample rate, as in libebur128
+        let rate = sample_rate as f64;
+
+        let f0 = 1681.974450955533;
+        let gain = 3.999843853973347;
+        let q = 0.7071752369554196;
+        let k = (PI * f0 / rate).tan();
+        let vh = 10.0_f64.powf(gain / 20.0);
+        let vb = vh.powf(0.4996667741545416);
+        let a0 = 1.0 + k / q + k * k;
+        let shelf = Biquad::new(
+            [
+                (vh + vb * k / q + k * k) / a0,
+                2.0 * (k * k - vh) / a0,
+                (vh - vb * k / q + k * k) / a0,
+            ],
+            [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        let f0 = 38.13547087602444;
+        let q = 0.5003270373238773;
+        let k = (PI * f0 / rate).tan();
+        let a0 = 1.0 + k / q + k * k;
+        let high_pass = Biquad::new(
+            [1.0, -2.0, 1.0],
+            [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        );
+
+        LoudnessMeter {
+            shelf,
+            high_pass,
+            step_length: (rate * STEP_SECONDS).round() as usize,
+            step_energy: 0.0,
+            step_count: 0,
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, sample: f64) {
+        let weighted = self.high_pass.process(self.shelf.process(sample));
+        self.step_energy += weighted * weighted;
+        self.step_count += 1;
+        if self.step_count == self.step_length {
+            self.steps.push(self.step_energy / self.step_length as f64);
+            self.step_energy = 0.0;
+            self.step_count = 0;
+        }
+    }
+
+    /// Gated loudness in LUFS, None if no block is above the absolute gate
+    pub fn integrated_loudness(&self) -> Option<f64> {
+        let blocks: Vec<f64> = self
+            .steps
+            .windows(BLOCK_STEPS)
+            .map(|steps| steps.iter().sum::<f64>() / BLOCK_STEPS as f64)
+            .filter(|energy| loudness(*energy) > ABSOLUTE_GATE)
+            .collect();
+        if blocks.is_empty() {
+            return None;
+        }
+
+        let relative_gate = loudness(mean(&blocks)) + RELATIVE_GATE;
+        let gated: Vec<f64> = blocks
+            .into_iter()
+            .filter(|energy| loudness(*energy) > relative_gate)
+            .collect();
+        Some(loudness(mean(&gated)))
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn loudness(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+pub fn db_to_gain(db: f64) -> f64 {
+    10.0_f64.powf(db / 20.0)
+}
+
+pub fn gain_to_db(gain: f64) -> f64 {
+    20.0 * gain.log10()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measure(samples: impl Iterator<Item = f64>) -> Option<f64> {
+        let mut meter = LoudnessMeter::new(16000);
+        samples.for_each(|sample| meter.push(sample));
+        meter.integrated_loudness()
+    }
+
+    fn tone(amplitude: f64, seconds: f64) -> impl Iterator<Item = f64> {
+        (0..(16000.0 * seconds) as usize)
+            .map(move |i| amplitude * (2.0 * PI * 1000.0 * i as f64 / 16000.0).sin())
+    }
+
+    #[test]
+    fn full_scale_tone_is_minus_three_lufs() {
+        let measured = measure(tone(1.0, 5.0)).unwrap();
+        assert!((measured + 3.01).abs() < 0.1, "{} LUFS", measured);
+    }
+
+    #[test]
+    fn silence_is_gated_out() {
+        assert_eq!(measure(std::iter::repeat_n(0.0, 16000 * 5)), None);
+
+        let with_pauses = tone(0.1, 5.0).chain(std::iter::repeat_n(0.0, 16000 * 20));
+        let measured = measure(with_pauses).unwrap();
+        assert!((measured + 23.01).abs() < 0.2, "{} LUFS", measured);
+    }
+}
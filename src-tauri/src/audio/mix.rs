@@ -0,0 +1,201 @@
+use std::{collections::VecDeque, path::PathBuf, time::Duration};
+
+use super::{
+    convert_audio::{MonoReader, OUTPUT_SAMPLE_RATE},
+    resample::Resampler,
+};
+
+/// Recording of the system audio, started `offset` after the microphone,
+/// or `lead` before it, which is left out
+pub struct SystemTrack {
+    pub filepath: PathBuf,
+    pub offset: Duration,
+    pub lead: Duration,
+    // where it is converted to when it is kept as its own track
+    pub output_path: PathBuf,
+}
+
+/// A second track read alongside the microphone, resampled to the output
+/// sample rate and aligned on the microphone timeline.
+/// A segmented track goes on in the files appended to it, as if they were one.
+pub struct AlignedTrack {
+    readers: VecDeque<MonoReader>,
+    resampler: Option<Resampler>,
+    // no file is appended after the ones it has
+    complete: bool,
+    pending: VecDeque<f64>,
+    // samples given as silence while the next file was not there yet, skipped once it is
+    missing: usize,
+    chunk: Vec<f64>,
+    resampled: Vec<f64>,
+}
+
+impl AlignedTrack {
+    pub fn open(
+        track: &SystemTrack,
+    ) -> Result<AlignedTrack, Box<dyn std::error::Error + Send + Sync>> {
+        let reader = MonoReader::open(&track.filepath)?;
+        let resampler = Some(Resampler::new(reader.sample_rate, OUTPUT_SAMPLE_RATE));
+
+        // silence until the track started, what it captured before the microphone did is skipped
+        let samples = |duration: Duration| {
+            (duration.as_secs_f64() * OUTPUT_SAMPLE_RATE as f64).round() as usize
+        };
+        Ok(AlignedTrack {
+            readers: VecDeque::from([reader]),
+            resampler,
+            complete: false,
+            pending: VecDeque::from(vec![0.0; samples(track.offset)]),
+            missing: samples(track.lead),
+            chunk: Vec::new(),
+            resampled: Vec::new(),
+        })
+    }
+
+    /// Goes on with the next file of the track, captured at the same sample rate
+    pub fn append(
+        &mut self,
+        filepath: &PathBuf,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.readers.push_back(MonoReader::open(filepath)?);
+        Ok(())
+    }
+
+    /// Once nothing is appended anymore, the end of the last file is resampled too
+    pub fn complete(&mut self) {
+        self.complete = true;
+    }
+
+    /// Appends exactly `count` samples to `output`, silence once the track is over
+    pub fn read(&mut self, count: usize, output: &mut Vec<f64>) -> Result<(), hound::Error> {
+        while self.pending.len() < count {
+            let Some(resampler) = self.resampler.as_mut() else {
+                break;
+            };
+
+            self.resampled.clear();
+            let read = match self.readers.front_mut() {
+                Some(reader) => reader.read_chunk(&mut self.chunk)?,
+                None => false,
+            };
+            if read {
+                resampler.process(&self.chunk, &mut self.resampled);
+            } else if self.readers.len() > 1 || (self.complete && !self.readers.is_empty()) {
+                self.readers.pop_front();
+                continue;
+            } else if self.complete {
+                if let Some(resampler) = self.resampler.take() {
+                    resampler.flush(&mut self.resampled);
+                }
+            } else {
+                // the next file is not appended yet
+                break;
+            }
+
+            let skipped = self.missing.min(self.resampled.len());
+            self.missing -= skipped;
+            self.pending.extend(self.resampled[skipped..].iter());
+        }
+
+        let available = count.min(self.pending.len());
+        output.extend(self.pending.drain(..available));
+        output.resize(output.len() + count - available, 0.0);
+        if !self.complete {
+            self.missing += count - available;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hound::{SampleFormat, WavSpec, WavWriter};
+    use std::path::Path;
+
+    fn write_track(name: &str, samples: usize) -> PathBuf {
+        let filepath =
+            std::env::temp_dir().join(format!("sniive_mix_{}_{}.wav", std::process::id(), name));
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: OUTPUT_SAMPLE_RATE,
+            bits_per_sample: 32,
+            sample_format: SampleFormat::Float,
+        };
+        let mut writer = WavWriter::create(&filepath, spec).unwrap();
+        for _ in 0..samples {
+            writer.write_sample(0.5f32).unwrap();
+        }
+        writer.finalize().unwrap();
+        filepath
+    }
+
+    fn track(filepath: &Path, offset_ms: u64, lead_ms: u64) -> SystemTrack {
+        SystemTrack {
+            filepath: filepath.to_path_buf(),
+            offset: Duration::from_millis(offset_ms),
+            lead: Duration::from_millis(lead_ms),
+            output_path: filepath.with_extension("pcm.wav"),
+        }
+    }
+
+    // where the track is heard, as the first sample and the number of samples
+    fn heard(samples: &[f64]) -> (usize, usize) {
+        let first = samples.iter().position(|sample| *sample > 0.25).unwrap();
+        (
+            first,
+            samples.iter().filter(|sample| **sample > 0.25).count(),
+        )
+    }
+
+    #[test]
+    fn track_starts_after_its_offset() {
+        let filepath = write_track("offset", 16000);
+        let mut aligned = AlignedTrack::open(&track(&filepath, 500, 0)).unwrap();
+        aligned.complete();
+
+        let mut samples = Vec::new();
+        aligned.read(4000, &mut samples).unwrap();
+        aligned.read(36000, &mut samples).unwrap();
+        assert_eq!(samples.len(), 40000);
+        let (first, count) = heard(&samples);
+        assert!(first.abs_diff(8000) <= 16, "{}", first);
+        assert!(count.abs_diff(16000) <= 32, "{}", count);
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[test]
+    fn lead_on_the_microphone_is_skipped() {
+        let filepath = write_track("lead", 16000);
+        let mut aligned = AlignedTrack::open(&track(&filepath, 0, 250)).unwrap();
+        aligned.complete();
+
+        let mut samples = Vec::new();
+        aligned.read(20000, &mut samples).unwrap();
+        let (first, count) = heard(&samples);
+        assert!(first <= 16, "{}", first);
+        assert!(count.abs_diff(12000) <= 32, "{}", count);
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[test]
+    fn appended_files_stay_in_place() {
+        let first_filepath = write_track("first", 8000);
+        let second_filepath = write_track("second", 8000);
+        let mut aligned = AlignedTrack::open(&track(&first_filepath, 0, 0)).unwrap();
+
+        // the second file comes late, what was read as silence meanwhile is not heard after it
+        let mut samples = Vec::new();
+        aligned.read(12000, &mut samples).unwrap();
+        aligned.append(&second_filepath).unwrap();
+        aligned.complete();
+        aligned.read(12000, &mut samples).unwrap();
+
+        assert_eq!(samples.len(), 24000);
+        let (_, count) = heard(&samples[12000..]);
+        assert!(count.abs_diff(4000) <= 32, "{}", count);
+        assert!(samples[16100..].iter().all(|sample| sample.abs() < 0.25));
+        std::fs::remove_file(first_filepath).unwrap();
+        std::fs::remove_file(second_filepath).unwrap();
+    }
+}
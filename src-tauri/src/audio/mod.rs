@@ -1,2 +1,27 @@
+pub mod alignment;
+pub mod audio_settings;
+pub mod biquad;
 pub mod convert_audio;
+pub mod cue_sheet;
+pub mod device_test;
+pub mod dsp_chain;
+pub mod ducking;
+pub mod encode;
 pub mod audio_controller;
+pub mod capture;
+pub mod flac;
+pub mod input_config;
+pub mod input_recovery;
+pub mod level_meter;
+pub mod limiter;
+pub mod loopback;
+pub mod loudness;
+pub mod mix;
+pub mod noise_gate;
+pub mod noise_suppression;
+pub mod opus;
+pub mod resample;
+pub mod segments;
+pub mod trim;
+pub mod vad;
+pub mod wav_repair;
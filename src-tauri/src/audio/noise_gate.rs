@@ -0,0 +1,62 @@
+// the gate opens within a millisecond, and stays open a little after the level drops
+const ATTACK_SECONDS: f64 = 0.001;
+const HOLD_SECONDS: f64 = 0.1;
+const ENVELOPE_DECAY_SECONDS: f64 = 0.01;
+
+/// Attenuates the signal while its level stays under a threshold
+pub struct NoiseGate {
+    threshold: f64,
+    closed_gain: f64,
+    attack: f64,
+    release: f64,
+    envelope_decay: f64,
+    hold: usize,
+    envelope: f64,
+    hold_count: usize,
+    gain: f64,
+}
+
+impl NoiseGate {
+    pub fn new(
+        sample_rate: u32,
+        threshold_db: f64,
+        attenuation_db: f64,
+        release_ms: u64,
+    ) -> NoiseGate {
+        let rate = sample_rate as f64;
+        let coefficient = |seconds: f64| 1.0 - (-1.0 / (rate * seconds)).exp();
+        NoiseGate {
+            threshold: 10.0_f64.powf(threshold_db / 20.0),
+            closed_gain: 10.0_f64.powf(-attenuation_db / 20.0),
+            attack: coefficient(ATTACK_SECONDS),
+            release: coefficient(release_ms.max(1) as f64 / 1000.0),
+            envelope_decay: 1.0 - coefficient(ENVELOPE_DECAY_SECONDS),
+            hold: (rate * HOLD_SECONDS) as usize,
+            envelope: 0.0,
+            hold_count: 0,
+            gain: 1.0,
+        }
+    }
+
+    pub fn process(&mut self, sample: f64) -> f64 {
+        self.envelope = sample.abs().max(self.envelope * self.envelope_decay);
+
+        let target = if self.envelope > self.threshold {
+            self.hold_count = self.hold;
+            1.0
+        } else if self.hold_count > 0 {
+            self.hold_count -= 1;
+            1.0
+        } else {
+            self.closed_gain
+        };
+
+        let rate = if target > self.gain {
+            self.attack
+        } else {
+            self.release
+        };
+        self.gain += (target - self.gain) * rate;
+        sample * self.gain
+    }
+}
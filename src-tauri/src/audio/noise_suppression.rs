@@ -0,0 +1,195 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use realfft::{num_complex::Complex, ComplexToReal, RealFftPlanner, RealToComplex};
+
+// 32ms frames at 16kHz, half overlapping
+const FRAME: usize = 512;
+const HOP: usize = FRAME / 2;
+// the noise is over-estimated a little, which leaves less residual "musical" noise
+const OVER_SUBTRACTION: f64 = 3.0;
+// bins this far above the noise estimate are considered signal and do not update it
+const SIGNAL_RATIO: f64 = 4.0;
+const NOISE_SMOOTHING: f64 = 0.9;
+// under signal, the noise estimate can still rise by about 3dB/s
+const NOISE_RISE: f64 = 1.011;
+const GAIN_SMOOTHING: f64 = 0.5;
+
+/// Spectral subtraction noise suppression: the stationary noise spectrum is averaged
+/// over the bins that look like noise and removed from every frame, down to a gain floor.
+/// The output is delayed by HOP samples, which `process` and `flush` compensate.
+pub struct NoiseSuppressor {
+    fft: Arc<dyn RealToComplex<f64>>,
+    ifft: Arc<dyn ComplexToReal<f64>>,
+    window: Vec<f64>,
+    min_gain: f64,
+    frame: Vec<f64>,
+    hop: Vec<f64>,
+    overlap: Vec<f64>,
+    spectrum: Vec<Complex<f64>>,
+    time: Vec<f64>,
+    noise: Vec<f64>,
+    gains: Vec<f64>,
+    first_frame: bool,
+    // output samples still to drop to compensate the delay
+    skip: usize,
+    input_count: usize,
+    output_count: usize,
+}
+
+impl NoiseSuppressor {
+    pub fn new(reduction_db: f64) -> NoiseSuppressor {
+        let mut planner = RealFftPlanner::<f64>::new();
+        let fft = planner.plan_fft_forward(FRAME);
+        let ifft = planner.plan_fft_inverse(FRAME);
+        let spectrum = fft.make_output_vec();
+        let bins = spectrum.len();
+
+        // square root of a periodic Hann window, applied before and after the processing,
+        // so that the overlapping frames add back to the input when nothing is removed
+        let window = (0..FRAME)
+            .map(|i| (0.5 - 0.5 * (2.0 * PI * i as f64 / FRAME as f64).cos()).sqrt())
+            .collect();
+
+        NoiseSuppressor {
+            fft,
+            ifft,
+            window,
+            min_gain: 10.0_f64.powf(-reduction_db / 20.0),
+            frame: vec![0.0; FRAME],
+            hop: Vec::with_capacity(HOP),
+            overlap: vec![0.0; FRAME],
+            spectrum,
+            time: vec![0.0; FRAME],
+            noise: vec![0.0; bins],
+            gains: vec![1.0; bins],
+            first_frame: true,
+            skip: HOP,
+            input_count: 0,
+            output_count: 0,
+        }
+    }
+
+    pub fn process(&mut self, input: &[f64], output: &mut Vec<f64>) {
+        self.input_count += input.len();
+        for sample in input {
+            self.hop.push(*sample);
+            if self.hop.len() == HOP {
+                self.process_hop(output);
+            }
+        }
+    }
+
+    /// Outputs the samples still held in the frames
+    pub fn flush(mut self, output: &mut Vec<f64>) {
+        let missing = self.input_count - self.output_count;
+        let mut tail = Vec::with_capacity(FRAME);
+        self.process(&[0.0; FRAME], &mut tail);
+        output.extend(tail.into_iter().take(missing));
+    }
+
+    fn process_hop(&mut self, output: &mut Vec<f64>) {
+        self.frame.copy_within(HOP.., 0);
+        self.frame[FRAME - HOP..].copy_from_slice(&self.hop);
+        self.hop.clear();
+
+        for (sample, (input, window)) in self
+            .time
+            .iter_mut()
+            .zip(self.frame.iter().zip(self.window.iter()))
+        {
+            *sample = input * window;
+        }
+        // the buffers are sized by the planner, this cannot fail
+        let _ = self.fft.process(&mut self.time, &mut self.spectrum);
+
+        for bin in 0..self.spectrum.len() {
+            let power = self.spectrum[bin].norm_sqr();
+            self.noise[bin] = if self.first_frame {
+                power
+            } else if power < SIGNAL_RATIO * self.noise[bin] {
+                NOISE_SMOOTHING * self.noise[bin] + (1.0 - NOISE_SMOOTHING) * power
+            } else {
+                self.noise[bin] * NOISE_RISE
+            };
+
+            let gain = if power > 0.0 {
+                (1.0 - OVER_SUBTRACTION * self.noise[bin] / power)
+                    .max(self.min_gain * self.min_gain)
+                    .sqrt()
+            } else {
+                self.min_gain
+            };
+            self.gains[bin] = GAIN_SMOOTHING * self.gains[bin] + (1.0 - GAIN_SMOOTHING) * gain;
+            self.spectrum[bin] *= self.gains[bin];
+        }
+        self.first_frame = false;
+
+        // the inverse transform needs purely real DC and nyquist bins
+        let last = self.spectrum.len() - 1;
+        self.spectrum[0].im = 0.0;
+        self.spectrum[last].im = 0.0;
+        let _ = self.ifft.process(&mut self.spectrum, &mut self.time);
+
+        for (i, sample) in self.time.iter().enumerate() {
+            self.overlap[i] += sample * self.window[i] / FRAME as f64;
+        }
+
+        let ready = self.skip.min(HOP);
+        self.skip -= ready;
+        output.extend_from_slice(&self.overlap[ready..HOP]);
+        self.output_count += HOP - ready;
+
+        self.overlap.copy_within(HOP.., 0);
+        self.overlap[FRAME - HOP..].fill(0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level_db(samples: &[f64]) -> f64 {
+        10.0 * (samples.iter().map(|s| s * s).sum::<f64>() / samples.len() as f64).log10()
+    }
+
+    #[test]
+    fn removes_stationary_noise_and_keeps_tone() {
+        let mut seed: u32 = 7;
+        let mut noise = move || {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            (seed as f64 / u32::MAX as f64 - 0.5) * 0.02
+        };
+        // 2s of noise, then 1s of tone over the same noise
+        let input: Vec<f64> = (0..48000)
+            .map(|i| {
+                let tone = if i >= 32000 {
+                    0.3 * (2.0 * PI * 440.0 * i as f64 / 16000.0).sin()
+                } else {
+                    0.0
+                };
+                tone + noise()
+            })
+            .collect();
+
+        let mut suppressor = NoiseSuppressor::new(20.0);
+        let mut output = Vec::new();
+        for chunk in input.chunks(1000) {
+            suppressor.process(chunk, &mut output);
+        }
+        suppressor.flush(&mut output);
+        assert_eq!(output.len(), input.len());
+
+        let noise_reduction = level_db(&input[16000..32000]) - level_db(&output[16000..32000]);
+        assert!(
+            noise_reduction > 12.0,
+            "noise reduced by {} dB",
+            noise_reduction
+        );
+        let tone_change = level_db(&input[36000..46000]) - level_db(&output[36000..46000]);
+        assert!(
+            tone_change.abs() < 1.0,
+            "tone changed by {} dB",
+            tone_change
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use std::io::Write;
+
+use audiopus::{coder::Encoder, Application, Bitrate, Channels, SampleRate};
+use ogg::{PacketWriteEndInfo, PacketWriter};
+
+// 20ms frames at 16kHz
+const FRAME_SAMPLES: usize = 320;
+// recommended maximum size of an opus packet
+const MAX_PACKET_SIZE: usize = 4000;
+// granule positions of ogg opus always count samples at 48kHz
+const GRANULE_RATE: u64 = 48000;
+
+/// Writes 16kHz mono audio as Opus packets in an Ogg container (RFC 7845)
+pub struct OpusWriter<W: Write> {
+    packets: PacketWriter<W>,
+    encoder: Encoder,
+    serial: u32,
+    frame: Vec<i16>,
+    packet: Vec<u8>,
+    pre_skip: u64,
+    total_samples: u64,
+    encoded_samples: u64,
+}
+
+impl<W: Write> OpusWriter<W> {
+    pub fn new(
+        writer: W,
+        bitrate_kbps: u32,
+    ) -> Result<OpusWriter<W>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut encoder = Encoder::new(SampleRate::Hz16000, Channels::Mono, Application::Voip)?;
+        encoder.set_bitrate(Bitrate::BitsPerSecond(bitrate_kbps as i32 * 1000))?;
+        let pre_skip = encoder.lookahead()? as u64 * GRANULE_RATE / 16000;
+
+        let serial = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .subsec_nanos();
+        let mut packets = PacketWriter::new(writer);
+
+        let mut head = Vec::with_capacity(19);
+        head.extend_from_slice(b"OpusHead");
+        head.push(1); // version
+        head.push(1); // channels
+        head.extend_from_slice(&(pre_skip as u16).to_le_bytes());
+        head.extend_from_slice(&16000u32.to_le_bytes());
+        head.extend_from_slice(&0i16.to_le_bytes()); // output gain
+        head.push(0); // mono / stereo mapping
+        packets.write_packet(
+            head.into_boxed_slice(),
+            serial,
+            PacketWriteEndInfo::EndPage,
+            0,
+        )?;
+
+        let vendor = format!("Sniive {}", env!("CARGO_PKG_VERSION"));
+        let mut tags = Vec::new();
+        tags.extend_from_slice(b"OpusTags");
+        tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+        tags.extend_from_slice(vendor.as_bytes());
+        tags.extend_from_slice(&0u32.to_le_bytes()); // no comments
+        packets.write_packet(
+            tags.into_boxed_slice(),
+            serial,
+            PacketWriteEndInfo::EndPage,
+            0,
+        )?;
+
+        Ok(OpusWriter {
+            packets,
+            encoder,
+            serial,
+            frame: Vec::with_capacity(FRAME_SAMPLES),
+            packet: vec![0; MAX_PACKET_SIZE],
+            pre_skip,
+            total_samples: 0,
+            encoded_samples: 0,
+        })
+    }
+
+    pub fn write_sample(
+        &mut self,
+        sample: i16,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.frame.push(sample);
+        self.total_samples += 1;
+        if self.frame.len() == FRAME_SAMPLES {
+            self.write_frame(PacketWriteEndInfo::NormalPacket)?;
+        }
+        Ok(())
+    }
+
+    pub fn finalize(mut self) -> Result<W, Box<dyn std::error::Error + Send + Sync>> {
+        // the encoder delay is flushed with silence, the decoder trims it with the last granule position
+        let end = self.total_samples + self.pre_skip * 16000 / GRANULE_RATE;
+        while self.encoded_samples + (FRAME_SAMPLES as u64) < end {
+            self.frame.resize(FRAME_SAMPLES, 0);
+            self.write_frame(PacketWriteEndInfo::NormalPacket)?;
+        }
+        self.frame.resize(FRAME_SAMPLES, 0);
+        self.write_frame(PacketWriteEndInfo::EndStream)?;
+
+        let mut writer = self.packets.into_inner();
+        writer.flush()?;
+        Ok(writer)
+    }
+
+    fn write_frame(
+        &mut self,
+        end_info: PacketWriteEndInfo,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let length = self.encoder.encode(&self.frame, &mut self.packet)?;
+        self.frame.clear();
+        self.encoded_samples += FRAME_SAMPLES as u64;
+
+        let last_granule = self.pre_skip + self.total_samples * GRANULE_RATE / 16000;
+        let granule = (self.encoded_samples * GRANULE_RATE / 16000).min(last_granule);
+        let granule = match end_info {
+            PacketWriteEndInfo::EndStream => last_granule,
+            _ => granule,
+        };
+        self.packets
+            .write_packet(self.packet[..length].into(), self.serial, end_info, granule)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use audiopus::{coder::Decoder, packet::Packet, MutSignals};
+    use ogg::PacketReader;
+
+    use super::*;
+
+    #[test]
+    fn decodes_to_the_written_samples() {
+        let length = 16000 + 123;
+        let samples: Vec<i16> = (0..length)
+            .map(|i| {
+                let t = i as f64 / 16000.0;
+                (8000.0 * (t * 440.0 * std::f64::consts::TAU).sin()) as i16
+            })
+            .collect();
+        let mut writer = OpusWriter::new(Cursor::new(Vec::new()), 32).unwrap();
+        for sample in &samples {
+            writer.write_sample(*sample).unwrap();
+        }
+        let ogg = writer.finalize().unwrap().into_inner();
+
+        let mut packets = PacketReader::new(Cursor::new(ogg));
+        let head = packets.read_packet().unwrap().unwrap();
+        assert_eq!(&head.data[..8], b"OpusHead");
+        let pre_skip = u16::from_le_bytes([head.data[10], head.data[11]]) as u64;
+        let tags = packets.read_packet().unwrap().unwrap();
+        assert_eq!(&tags.data[..8], b"OpusTags");
+
+        let mut decoder = Decoder::new(SampleRate::Hz16000, Channels::Mono).unwrap();
+        let mut decoded = Vec::new();
+        let mut frame = vec![0; FRAME_SAMPLES];
+        let mut last_granule = 0;
+        while let Some(packet) = packets.read_packet().unwrap() {
+            let count = decoder
+                .decode(
+                    Some(Packet::try_from(&packet.data).unwrap()),
+                    MutSignals::try_from(&mut frame).unwrap(),
+                    false,
+                )
+                .unwrap();
+            decoded.extend_from_slice(&frame[..count]);
+            if packet.last_in_stream() {
+                last_granule = packet.absgp_page();
+            }
+        }
+
+        // the last granule position trims the encoder delay and the padding of the last frame
+        assert_eq!(
+            last_granule,
+            pre_skip + length as u64 * GRANULE_RATE / 16000
+        );
+        let start = (pre_skip * 16000 / GRANULE_RATE) as usize;
+        assert!(decoded.len() >= start + length);
+        let decoded = &decoded[start..start + length];
+
+        // lossy, but the same signal once the encoder settled
+        let settled = 1600..length;
+        let dot = |a: &[i16], b: &[i16]| -> f64 {
+            a[settled.clone()]
+                .iter()
+                .zip(&b[settled.clone()])
+                .map(|(a, b)| *a as f64 * *b as f64)
+                .sum()
+        };
+        let correlation =
+            dot(decoded, &samples) / (dot(decoded, decoded) * dot(&samples, &samples)).sqrt();
+        assert!(correlation > 0.9, "correlation {}", correlation);
+    }
+}
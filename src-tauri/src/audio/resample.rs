@@ -0,0 +1,231 @@
+use std::f64::consts::PI;
+
+// attenuation of everything above the output nyquist frequency, in dB
+const STOPBAND_ATTENUATION: f64 = 80.0;
+// fraction of the output nyquist frequency kept untouched, the rest is the transition band
+const PASSBAND: f64 = 0.875;
+// number of precomputed fractional positions between two input samples
+const PHASES: usize = 256;
+
+/// Band-limited resampler: a Kaiser-windowed sinc low-pass filter evaluated at
+/// the output positions from a polyphase table, so that frequencies above the
+/// output nyquist frequency are removed instead of being folded back.
+/// Samples can be pushed in chunks, the state is kept between calls.
+pub struct Resampler {
+    // input samples per output sample, as the reduced fraction step_num / step_den
+    step_num: u64,
+    step_den: u64,
+    half_taps: usize,
+    // PHASES + 1 rows of 2 * half_taps coefficients
+    table: Vec<f64>,
+    buffer: Vec<f64>,
+    // position of the next output sample: buffer[index] + remainder / step_den
+    index: usize,
+    remainder: u64,
+    input_count: u64,
+    output_count: u64,
+}
+
+impl Resampler {
+    pub fn new(input_rate: u32, output_rate: u32) -> Resampler {
+        let divisor = gcd(input_rate as u64, output_rate as u64);
+        let (step_num, step_den) = (input_rate as u64 / divisor, output_rate as u64 / divisor);
+
+        // frequencies relative to the input sample rate
+        let nyquist = input_rate.min(output_rate) as f64 / 2.0 / input_rate as f64;
+        let transition = nyquist * (1.0 - PASSBAND);
+        let cutoff = nyquist - transition / 2.0;
+
+        // Kaiser window design formulas
+        let beta = 0.1102 * (STOPBAND_ATTENUATION - 8.7);
+        let length = (STOPBAND_ATTENUATION - 8.0) / (2.285 * 2.0 * PI * transition);
+        let half_taps = (length / 2.0).ceil() as usize + 1;
+
+        let taps = 2 * half_taps;
+        let mut table = vec![0.0; (PHASES + 1) * taps];
+        for phase in 0..=PHASES {
+            let fraction = phase as f64 / PHASES as f64;
+            for tap in 0..taps {
+                // distance between the output position and the input sample
+                let distance = fraction - (tap as f64 - half_taps as f64 + 1.0);
+                table[phase * taps + tap] = 2.0 * cutoff
+                    * sinc(2.0 * cutoff * distance)
+                    * kaiser(distance / half_taps as f64, beta);
+            }
+        }
+
+        Resampler {
+            step_num,
+            step_den,
+            half_taps,
+            table,
+            // the signal is considered silent before the first sample
+            buffer: vec![0.0; half_taps - 1],
+            index: half_taps - 1,
+            remainder: 0,
+            input_count: 0,
+            output_count: 0,
+        }
+    }
+
+    /// Resamples `input`, appending to `output` every sample that can already be computed
+    pub fn process(&mut self, input: &[f64], output: &mut Vec<f64>) {
+        self.input_count += input.len() as u64;
+        self.buffer.extend_from_slice(input);
+
+        let taps = 2 * self.half_taps;
+        while self.index + self.half_taps < self.buffer.len() {
+            let position = self.remainder as f64 / self.step_den as f64 * PHASES as f64;
+            let phase = (position as usize).min(PHASES - 1);
+            let weight = position - phase as f64;
+
+            let samples = &self.buffer[self.index + 1 - self.half_taps..=self.index + self.half_taps];
+            let low = &self.table[phase * taps..(phase + 1) * taps];
+            let high = &self.table[(phase + 1) * taps..(phase + 2) * taps];
+            let mut sample = 0.0;
+            for tap in 0..taps {
+                sample += samples[tap] * (low[tap] + (high[tap] - low[tap]) * weight);
+            }
+            output.push(sample);
+            self.output_count += 1;
+
+            self.remainder += self.step_num;
+            self.index += (self.remainder / self.step_den) as usize;
+            self.remainder %= self.step_den;
+        }
+
+        // only keep what the next output samples need
+        let consumed = (self.index + 1 - self.half_taps).min(self.buffer.len());
+        self.buffer.drain(..consumed);
+        self.index -= consumed;
+    }
+
+    /// Pads the end of the signal with silence and outputs the remaining samples
+    pub fn flush(mut self, output: &mut Vec<f64>) {
+        let expected = (self.input_count * self.step_den).div_ceil(self.step_num);
+        let missing = (expected - self.output_count) as usize;
+
+        let mut tail = Vec::with_capacity(missing + 1);
+        self.process(&vec![0.0; self.half_taps + 1], &mut tail);
+        output.extend(tail.into_iter().take(missing));
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Kaiser window over [-1, 1]
+fn kaiser(x: f64, beta: f64) -> f64 {
+    if x.abs() > 1.0 {
+        return 0.0;
+    }
+    bessel_i0(beta * (1.0 - x * x).sqrt()) / bessel_i0(beta)
+}
+
+/// Zeroth order modified Bessel function of the first kind, from its power series
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half_x = x / 2.0;
+    for k in 1..50 {
+        term *= half_x / k as f64;
+        sum += term * term;
+        if term * term < sum * 1e-17 {
+            break;
+        }
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(frequency: f64, sample_rate: u32, seconds: f64) -> Vec<f64> {
+        let length = (sample_rate as f64 * seconds) as usize;
+        (0..length)
+            .map(|i| 0.5 * (2.0 * PI * frequency * i as f64 / sample_rate as f64).sin())
+            .collect()
+    }
+
+    fn resample(input: &[f64], input_rate: u32, output_rate: u32) -> Vec<f64> {
+        let mut resampler = Resampler::new(input_rate, output_rate);
+        let mut output = Vec::new();
+        // odd chunk sizes to exercise the streaming state
+        for chunk in input.chunks(1237) {
+            resampler.process(chunk, &mut output);
+        }
+        resampler.flush(&mut output);
+        output
+    }
+
+    /// RMS level in dB relative to the 0.5 amplitude test tones, ignoring the filter edges
+    fn level(samples: &[f64]) -> f64 {
+        let margin = samples.len() / 10;
+        let middle = &samples[margin..samples.len() - margin];
+        let rms = (middle.iter().map(|s| s * s).sum::<f64>() / middle.len() as f64).sqrt();
+        20.0 * (rms / (0.5 / 2.0_f64.sqrt())).log10()
+    }
+
+    #[test]
+    fn output_length_matches_rate_ratio() {
+        for (input_rate, input_length) in [(48000, 48000), (44100, 44101), (22050, 999)] {
+            let output = resample(&vec![0.0; input_length], input_rate, 16000);
+            let expected = (input_length as u64 * 16000).div_ceil(input_rate as u64);
+            assert_eq!(output.len() as u64, expected);
+        }
+    }
+
+    #[test]
+    fn passband_is_flat() {
+        for input_rate in [44100, 48000] {
+            for frequency in [100.0, 1000.0, 3000.0, 6500.0] {
+                let output = resample(&tone(frequency, input_rate, 1.0), input_rate, 16000);
+                let gain = level(&output);
+                assert!(gain.abs() < 0.1, "{} Hz at {} Hz: {} dB", frequency, input_rate, gain);
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_aliasing() {
+        // nearest-neighbour decimation would fold these back into the voice band
+        for input_rate in [44100, 48000] {
+            for frequency in [8500.0, 12000.0, 15000.0, 20000.0] {
+                let output = resample(&tone(frequency, input_rate, 1.0), input_rate, 16000);
+                let gain = level(&output);
+                assert!(gain < -70.0, "{} Hz at {} Hz: {} dB", frequency, input_rate, gain);
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_tone_frequency() {
+        let output = resample(&tone(1000.0, 44100, 1.0), 44100, 16000);
+        let margin = output.len() / 10;
+        let middle = &output[margin..output.len() - margin];
+
+        // correlation with the expected 1 kHz tone, zero if the pitch had drifted
+        let (mut sin, mut cos) = (0.0, 0.0);
+        for (i, sample) in middle.iter().enumerate() {
+            let angle = 2.0 * PI * 1000.0 * (i + margin) as f64 / 16000.0;
+            sin += sample * angle.sin();
+            cos += sample * angle.cos();
+        }
+        let amplitude = 2.0 * (sin * sin + cos * cos).sqrt() / middle.len() as f64;
+        assert!((amplitude - 0.5).abs() < 0.01, "amplitude {}", amplitude);
+    }
+}
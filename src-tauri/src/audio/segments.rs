@@ -0,0 +1,685 @@
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+use crate::{
+    app::{
+        app_state::{AppState, RecordingPause, StepTime},
+        session_journal::update_journal,
+    },
+    utils,
+};
+
+use super::{
+    alignment::AudioAlignment,
+    audio_settings::{AudioSettings, EncodingSettings},
+    capture::{segment_filepath, CapturedSegment},
+    convert_audio::{ConversionReport, SegmentConverter},
+    cue_sheet::{cue_piece, StepCue},
+    encode::{encode_audio, EncodedAudio},
+    mix::SystemTrack,
+    wav_repair::repair_wav_header,
+};
+
+// the conversion goes on from a segment to the next, only the upload is per segment
+pub const SEGMENT_SECONDS: u64 = 60;
+
+/// What the segments of a recording are converted with
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SegmentContext {
+    pub settings: AudioSettings,
+    pub audio_start_time: SystemTime,
+    // start of the system audio after the microphone by the clock, when the devices gave no capture times
+    pub system_offset: Duration,
+}
+
+/// A converted segment of the recording, uploaded once it has a link
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioSegment {
+    pub sequence: usize,
+    // position of the segment in the recording, before any silence is cut
+    pub start_ms: u64,
+    pub link: Option<String>,
+    pub system_link: Option<String>,
+    #[serde(flatten)]
+    pub report: ConversionReport,
+    #[serde(skip)]
+    pub files: EncodedAudio,
+}
+
+/// Where a captured file is converted to, beside it
+fn converted_path(filepath: &Path) -> PathBuf {
+    filepath.with_extension("pcm.wav")
+}
+
+/// Position of a moment in the captured audio, which leaves the pauses out
+fn audio_offset(
+    time: SystemTime,
+    audio_start_time: SystemTime,
+    pauses: &[RecordingPause],
+) -> Option<Duration> {
+    let mut offset = time.duration_since(audio_start_time).ok()?;
+    for pause in pauses.iter().filter(|pause| pause.start <= time) {
+        match pause.end {
+            Some(end) if end <= time => {
+                offset = offset.saturating_sub(end.duration_since(pause.start).ok()?)
+            }
+            // nothing was captured during the pause
+            _ => return None,
+        }
+    }
+    Some(offset)
+}
+
+/// Where each step is heard in the converted segments, with the padding of the settings around it.
+/// The capture times of the alignment place it on the frames it was heard in, when the devices gave any.
+pub fn step_cues(
+    context: &SegmentContext,
+    pauses: &[RecordingPause],
+    step_times: &[StepTime],
+    segments: &[AudioSegment],
+    alignment: Option<&AudioAlignment>,
+) -> Vec<StepCue> {
+    let padding = Duration::from_millis(context.settings.step_cues.padding_ms);
+    // a step that started before the audio, or ended during a pause, is cut to the audio around it
+    let offset = |time: SystemTime| {
+        alignment
+            .and_then(|alignment| alignment.offset(time))
+            .or_else(|| {
+                audio_offset(
+                    time.max(context.audio_start_time),
+                    context.audio_start_time,
+                    pauses,
+                )
+            })
+    };
+
+    step_times
+        .iter()
+        .enumerate()
+        .map(|(step, step_time)| {
+            let start = offset(step_time.start).unwrap_or_default();
+            let end = offset(step_time.end).unwrap_or(start).max(start);
+            let (start, end) = (start.saturating_sub(padding), end + padding);
+
+            let pieces = segments
+                .iter()
+                .filter_map(|segment| {
+                    // a segment ends where the next one starts, what its filters held back included
+                    let segment_start = Duration::from_millis(segment.start_ms);
+                    let segment_end = segments
+                        .iter()
+                        .map(|next| Duration::from_millis(next.start_ms))
+                        .filter(|next_start| *next_start > segment_start)
+                        .min()
+                        .unwrap_or(Duration::MAX);
+                    if end <= segment_start || start >= segment_end {
+                        return None;
+                    }
+                    let range = start.max(segment_start) - segment_start
+                        ..end.min(segment_end) - segment_start;
+                    cue_piece(
+                        segment.sequence,
+                        range.start.as_millis() as u64..range.end.as_millis() as u64,
+                        &segment.report.removed_ranges,
+                        segment.report.duration_ms,
+                        segment.files.format,
+                        &segment.files.frame_offsets,
+                    )
+                })
+                .collect();
+            StepCue { step, pieces }
+        })
+        .collect()
+}
+
+/// Where the system audio starts on the microphone timeline, from the capture times of their first frames:
+/// the offset it starts after the microphone, or the lead it has on it
+fn system_track_start(
+    microphone: &AudioAlignment,
+    system: &AudioAlignment,
+) -> Option<(Duration, Duration)> {
+    let start_us = |alignment: &AudioAlignment| {
+        let anchor = alignment.anchors.first()?;
+        let frame_us = anchor.frame * 1_000_000 / alignment.sample_rate as u64;
+        Some(anchor.time_us as i64 - frame_us as i64)
+    };
+    let offset_us = start_us(system)? - start_us(microphone)?;
+    let duration = Duration::from_micros(offset_us.unsigned_abs());
+    Some(match offset_us >= 0 {
+        true => (duration, Duration::ZERO),
+        false => (Duration::ZERO, duration),
+    })
+}
+
+/// The keystrokes heard in a part of the captured audio, as offsets from its start.
+/// The capture times of the alignment place them on the frames they were heard in, when the devices gave any.
+fn segment_keystrokes(
+    keystroke_times: &[SystemTime],
+    audio_start_time: SystemTime,
+    pauses: &[RecordingPause],
+    alignment: Option<&AudioAlignment>,
+    range: Range<Duration>,
+) -> Vec<Duration> {
+    keystroke_times
+        .iter()
+        .filter_map(|time| {
+            // nothing was captured of the keystrokes during a pause
+            let offset = audio_offset(*time, audio_start_time, pauses)?;
+            Some(
+                alignment
+                    .and_then(|alignment| alignment.offset(*time))
+                    .unwrap_or(offset),
+            )
+        })
+        .filter(|offset| range.contains(offset))
+        .map(|offset| offset - range.start)
+        .collect()
+}
+
+/// Converts and encodes the files of a segment, with what the converter carries from the previous ones
+fn convert_files(
+    converter: &mut SegmentConverter,
+    encoding: &EncodingSettings,
+    segment: &CapturedSegment,
+    start: Duration,
+    keystrokes: &[Duration],
+    system_track: Option<&SystemTrack>,
+) -> Result<AudioSegment, Box<dyn std::error::Error + Send + Sync>> {
+    // the captures are kept until the segment is journaled, a crash before that converts them again
+    let output_path = converted_path(&segment.filepath);
+    let report = converter.convert(
+        &segment.filepath,
+        &output_path,
+        start,
+        keystrokes,
+        system_track,
+        segment.last,
+    )?;
+
+    let (narration, frame_offsets) = encode_audio(&output_path, encoding)?;
+    let system = match system_track {
+        Some(system_track) if report.separate_system_track => {
+            Some(encode_audio(&system_track.output_path, encoding)?.0)
+        }
+        _ => None,
+    };
+
+    Ok(AudioSegment {
+        sequence: segment.sequence,
+        start_ms: start.as_millis() as u64,
+        link: None,
+        system_link: None,
+        report,
+        files: EncodedAudio {
+            format: encoding.format,
+            narration,
+            frame_offsets,
+            system,
+            captures: std::iter::once(segment.filepath.clone())
+                .chain(system_track.map(|system_track| system_track.filepath.clone()))
+                .collect(),
+        },
+    })
+}
+
+/// Converts and encodes a captured segment, with the keystrokes that fall in it.
+/// The converter goes on from the previous segment, a new one starts where the segment was captured.
+pub async fn convert_segment(
+    app_handle: &AppHandle,
+    context: &SegmentContext,
+    alignment: Option<&AudioAlignment>,
+    converter: &mut Option<SegmentConverter>,
+    segment: CapturedSegment,
+    system_segment: Option<CapturedSegment>,
+) -> Result<AudioSegment, Box<dyn std::error::Error + Send + Sync>> {
+    let state = app_handle
+        .try_state::<AppState>()
+        .ok_or("Failed to get AppState")?;
+
+    let segment_length = Duration::from_secs(SEGMENT_SECONDS);
+    let start = converter
+        .as_ref()
+        .and_then(|converter| converter.next_start())
+        .unwrap_or(segment_length * segment.sequence as u32);
+    let end = match segment.last {
+        true => Duration::MAX,
+        false => segment_length * (segment.sequence + 1) as u32,
+    };
+    let pauses = state.recording_pauses.lock().await.clone();
+    let keystrokes = segment_keystrokes(
+        &state.keystroke_times.lock().await,
+        context.audio_start_time,
+        &pauses,
+        alignment,
+        start..end,
+    );
+
+    // the start only places the first file of the system track, the next ones go on from it
+    let system_track = system_segment.map(|system_segment| {
+        let (offset, lead) = match system_segment.sequence {
+            0 => alignment
+                .and_then(|alignment| system_track_start(alignment, &system_segment.alignment))
+                .unwrap_or((context.system_offset, Duration::ZERO)),
+            _ => (Duration::ZERO, Duration::ZERO),
+        };
+        SystemTrack {
+            offset,
+            lead,
+            output_path: converted_path(&system_segment.filepath),
+            filepath: system_segment.filepath,
+        }
+    });
+
+    let last = segment.last;
+    let encoding = context.settings.encoding.clone();
+    let mut segment_converter = converter
+        .take()
+        .unwrap_or_else(|| SegmentConverter::new(context.settings.clone()));
+    let (segment_converter, result) = tauri::async_runtime::spawn_blocking(move || {
+        let result = convert_files(
+            &mut segment_converter,
+            &encoding,
+            &segment,
+            start,
+            &keystrokes,
+            system_track.as_ref(),
+        );
+        (segment_converter, result)
+    })
+    .await?;
+
+    // a failed conversion leaves the filters halfway through, the next segment starts over
+    if result.is_ok() && !last {
+        *converter = Some(segment_converter);
+    }
+    result
+}
+
+/// Removes what was captured for a segment, once it is journaled
+pub fn remove_captures(segment: &AudioSegment) {
+    for capture in segment
+        .files
+        .captures
+        .iter()
+        .filter(|capture| capture.exists())
+    {
+        if let Err(e) = std::fs::remove_file(capture) {
+            eprintln!("Failed to remove {}: {}", capture.display(), e);
+        }
+    }
+}
+
+/// Uploads the files of a segment that are not uploaded yet, and removes them once they are
+pub async fn upload_segment(
+    app_handle: &AppHandle,
+    segment: &mut AudioSegment,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    let files = &segment.files;
+    let extension = files.format.extension();
+    let content_type = files.format.content_type();
+
+    if segment.link.is_none() {
+        let upload_link =
+            utils::get_upload_link(app_handle, extension, content_type, Some(segment.sequence))
+                .await?;
+        if !utils::upload_file(&upload_link, &files.narration, content_type).await? {
+            return Ok(false);
+        }
+        std::fs::remove_file(&files.narration)?;
+        segment.link = upload_link.split('?').next().map(|link| link.to_string());
+    }
+
+    if let (Some(system_filepath), None) = (&files.system, &segment.system_link) {
+        let upload_link =
+            utils::get_upload_link(app_handle, extension, content_type, Some(segment.sequence))
+                .await?;
+        if !utils::upload_file(&upload_link, system_filepath, content_type).await? {
+            return Ok(false);
+        }
+        std::fs::remove_file(system_filepath)?;
+        segment.system_link = upload_link.split('?').next().map(|link| link.to_string());
+    }
+    Ok(true)
+}
+
+/// Converts and uploads the segments as the capture completes them, until it sends the last one.
+/// The segments that fail to upload are kept, to be uploaded again by `finish_recording`,
+/// and the sequences of those that fail to convert are returned, to be converted again from their captures.
+pub async fn upload_segments(
+    app_handle: AppHandle,
+    context: SegmentContext,
+    mut segments: UnboundedReceiver<CapturedSegment>,
+    mut system_segments: Option<UnboundedReceiver<CapturedSegment>>,
+) -> Result<(Vec<AudioSegment>, Vec<usize>), Box<dyn std::error::Error + Send + Sync>> {
+    let audio_context = context.clone();
+    update_journal(&app_handle, |journal| {
+        journal.audio_context = Some(audio_context)
+    })
+    .await;
+
+    let mut uploaded = Vec::new();
+    let mut failed = Vec::new();
+    let mut converter = None;
+    // the anchors of the microphone so far, the keystrokes of a segment are placed with them
+    let mut alignment = AudioAlignment::default();
+    while let Some(segment) = segments.recv().await {
+        alignment.sample_rate = segment.alignment.sample_rate;
+        alignment
+            .anchors
+            .extend_from_slice(&segment.alignment.anchors);
+        // the system capture moves to its next segment right after the microphone
+        let system_segment = match system_segments.as_mut() {
+            Some(system_segments) => system_segments.recv().await,
+            None => None,
+        };
+
+        let sequence = segment.sequence;
+        let mut segment = match convert_segment(
+            &app_handle,
+            &context,
+            Some(&alignment),
+            &mut converter,
+            segment,
+            system_segment,
+        )
+        .await
+        {
+            Ok(segment) => segment,
+            Err(e) => {
+                // its captures are kept, finish_recording converts them again
+                eprintln!("Failed to convert audio segment {}: {}", sequence, e);
+                failed.push(sequence);
+                continue;
+            }
+        };
+        match upload_segment(&app_handle, &mut segment).await {
+            Ok(true) => {}
+            Ok(false) => eprintln!("Failed to upload audio segment {}", segment.sequence),
+            Err(e) => eprintln!("Failed to upload audio segment {}: {}", segment.sequence, e),
+        }
+        uploaded.push(segment);
+
+        let audio_segments = uploaded.clone();
+        if update_journal(&app_handle, |journal| {
+            journal.audio_segments = audio_segments
+        })
+        .await
+        {
+            uploaded.iter().for_each(remove_captures);
+        }
+    }
+    Ok((uploaded, failed))
+}
+
+/// Fixes the headers of the wav files an interrupted recording left, so that they can be read
+pub fn repair_captured_audio(
+    audio_dir: &Path,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    for entry in std::fs::read_dir(audio_dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|extension| extension == "wav") {
+            if let Err(e) = repair_wav_header(&path) {
+                eprintln!("Failed to repair {}: {}", path.display(), e);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Converts what an interrupted recording captured next to the segments it had converted.
+/// The captures are only removed once their segment is journaled, so a segment that is not
+/// is converted again from them, whatever its conversion or encoding left.
+/// Returns the segments, and the sequences that failed to convert.
+pub async fn recover_segments(
+    app_handle: &AppHandle,
+    context: &SegmentContext,
+    alignment: Option<&AudioAlignment>,
+    audio_dir: &Path,
+    converted: Vec<AudioSegment>,
+) -> Result<(Vec<AudioSegment>, Vec<usize>), Box<dyn std::error::Error + Send + Sync>> {
+    repair_captured_audio(audio_dir)?;
+
+    let filepath = audio_dir.join("output.wav");
+    let system_filepath = audio_dir.join("system.wav");
+    let format = context.settings.encoding.format;
+    let extension = format.extension();
+
+    // the files of a segment are not journaled, they are where the encoding puts them,
+    // and its captures are left if the recording stopped before they were removed.
+    // where their flac frames start is lost with them, their cues are cut by time
+    let mut audio_segments: Vec<AudioSegment> = converted
+        .into_iter()
+        .map(|mut segment| {
+            let capture = segment_filepath(&filepath, segment.sequence);
+            let system_capture = segment_filepath(&system_filepath, segment.sequence);
+            segment.files = EncodedAudio {
+                format,
+                narration: converted_path(&capture).with_extension(extension),
+                frame_offsets: Vec::new(),
+                system: segment
+                    .report
+                    .separate_system_track
+                    .then(|| converted_path(&system_capture).with_extension(extension)),
+                captures: vec![capture, system_capture],
+            };
+            segment
+        })
+        .collect();
+
+    let mut captured_sequences: Vec<usize> = std::fs::read_dir(audio_dir)?
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            name.strip_prefix("output.")?
+                .strip_suffix(".wav")?
+                .parse()
+                .ok()
+        })
+        .filter(|sequence| {
+            !audio_segments
+                .iter()
+                .any(|segment| segment.sequence == *sequence)
+        })
+        .collect();
+    captured_sequences.sort();
+
+    let (recovered, failed) = convert_captures(
+        app_handle,
+        context,
+        alignment,
+        audio_dir,
+        &captured_sequences,
+    )
+    .await?;
+    audio_segments.extend(recovered);
+    audio_segments.sort_by_key(|segment| segment.sequence);
+    Ok((audio_segments, failed))
+}
+
+/// Converts the captured segments of the sequences, in order, from the files left in the audio directory.
+/// Returns the converted segments, and the sequences that failed, whose captures are kept.
+pub async fn convert_captures(
+    app_handle: &AppHandle,
+    context: &SegmentContext,
+    alignment: Option<&AudioAlignment>,
+    audio_dir: &Path,
+    sequences: &[usize],
+) -> Result<(Vec<AudioSegment>, Vec<usize>), Box<dyn std::error::Error + Send + Sync>> {
+    let filepath = audio_dir.join("output.wav");
+    let system_filepath = audio_dir.join("system.wav");
+    let extension = context.settings.encoding.format.extension();
+
+    // consecutive segments are converted as one, a converted one in between starts over
+    let mut converted = Vec::new();
+    let mut failed = Vec::new();
+    let mut converter = None;
+    for (i, &sequence) in sequences.iter().enumerate() {
+        if i == 0 || sequences[i - 1] + 1 != sequence {
+            converter = None;
+        }
+        let last = sequences.get(i + 1) != Some(&(sequence + 1));
+        let segment = CapturedSegment {
+            sequence,
+            filepath: segment_filepath(&filepath, sequence),
+            last,
+            alignment: AudioAlignment::default(),
+        };
+        let system_segment = Some(CapturedSegment {
+            sequence,
+            filepath: segment_filepath(&system_filepath, sequence),
+            last,
+            alignment: AudioAlignment::default(),
+        })
+        .filter(|system_segment| system_segment.filepath.exists());
+
+        // what an interrupted conversion or encoding left is made again
+        for captured in std::iter::once(&segment).chain(system_segment.as_ref()) {
+            let output_path = converted_path(&captured.filepath);
+            let leftovers = [
+                output_path.with_extension("mono.wav"),
+                output_path.with_extension(extension),
+                output_path,
+            ];
+            for leftover in leftovers.iter().filter(|leftover| leftover.exists()) {
+                std::fs::remove_file(leftover)?;
+            }
+        }
+
+        match convert_segment(
+            app_handle,
+            context,
+            alignment,
+            &mut converter,
+            segment,
+            system_segment,
+        )
+        .await
+        {
+            Ok(segment) => converted.push(segment),
+            Err(e) => {
+                eprintln!("Failed to convert audio segment {}: {}", sequence, e);
+                failed.push(sequence);
+            }
+        }
+    }
+    Ok((converted, failed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::alignment::ClockAnchor;
+
+    fn at(seconds: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn leaves_pauses_out_of_offsets() {
+        let pauses = [
+            RecordingPause {
+                start: at(10),
+                end: Some(at(15)),
+            },
+            RecordingPause {
+                start: at(20),
+                end: None,
+            },
+        ];
+
+        assert_eq!(
+            audio_offset(at(5), at(0), &pauses),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(audio_offset(at(12), at(0), &pauses), None);
+        assert_eq!(
+            audio_offset(at(18), at(0), &pauses),
+            Some(Duration::from_secs(13))
+        );
+        assert_eq!(audio_offset(at(25), at(0), &pauses), None);
+        assert_eq!(audio_offset(at(0), at(1), &pauses), None);
+    }
+
+    #[test]
+    fn places_the_system_track_by_its_capture_times() {
+        // the microphone started at 10s, its anchor is a second later
+        let microphone = AudioAlignment {
+            sample_rate: 48000,
+            anchors: vec![ClockAnchor::new(48000, at(11))],
+        };
+        let system = |time: SystemTime| AudioAlignment {
+            sample_rate: 44100,
+            anchors: vec![ClockAnchor::new(0, time)],
+        };
+
+        assert_eq!(
+            system_track_start(&microphone, &system(at(10) + Duration::from_millis(300))),
+            Some((Duration::from_millis(300), Duration::ZERO))
+        );
+        assert_eq!(
+            system_track_start(&microphone, &system(at(10) - Duration::from_millis(200))),
+            Some((Duration::ZERO, Duration::from_millis(200)))
+        );
+        assert_eq!(
+            system_track_start(&microphone, &AudioAlignment::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn keeps_the_keystrokes_of_a_segment() {
+        let pauses = [RecordingPause {
+            start: at(70),
+            end: Some(at(80)),
+        }];
+        let keystroke_times = [at(30), at(65), at(75), at(90), at(130), at(200)];
+
+        let keystrokes = segment_keystrokes(
+            &keystroke_times,
+            at(0),
+            &pauses,
+            None,
+            Duration::from_secs(60)..Duration::from_secs(120),
+        );
+        assert_eq!(
+            keystrokes,
+            [Duration::from_secs(5), Duration::from_secs(20)]
+        );
+
+        let keystrokes = segment_keystrokes(
+            &keystroke_times,
+            at(0),
+            &pauses,
+            None,
+            Duration::from_secs(120)..Duration::MAX,
+        );
+        assert_eq!(keystrokes, [Duration::ZERO, Duration::from_secs(70)]);
+        // the device delivered its first frame two seconds after the audio started
+        let alignment = AudioAlignment {
+            sample_rate: 1000,
+            anchors: vec![ClockAnchor::new(0, at(2))],
+        };
+        let keystrokes = segment_keystrokes(
+            &keystroke_times,
+            at(0),
+            &[],
+            Some(&alignment),
+            Duration::from_secs(60)..Duration::from_secs(120),
+        );
+        assert_eq!(
+            keystrokes,
+            [
+                Duration::from_secs(3),
+                Duration::from_secs(13),
+                Duration::from_secs(28)
+            ]
+        );
+    }
+}
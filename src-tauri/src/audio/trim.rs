@@ -0,0 +1,181 @@
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use super::{audio_settings::SilenceSettings, vad::SpeechSegment};
+
+// fade applied on both sides of a cut, so that it does not click
+const FADE_SECONDS: f64 = 0.005;
+
+/// Part of the recording removed from the uploaded audio, in milliseconds from the start of the recording
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemovedRange {
+    pub start_ms: u64,
+    pub end_ms: u64,
+}
+
+/// Sample ranges of the recording kept in the uploaded audio
+#[derive(Clone)]
+pub struct TrimPlan {
+    sample_rate: u32,
+    length: usize,
+    kept: Vec<Range<usize>>,
+    // index of the kept range reached by `gain`
+    cursor: usize,
+}
+
+impl TrimPlan {
+    /// Keeps the speech with some padding, and the silences the settings do not ask to remove
+    pub fn new(
+        sample_rate: u32,
+        length: usize,
+        speech: &[(usize, usize)],
+        settings: &SilenceSettings,
+    ) -> TrimPlan {
+        let to_samples = |ms: u64| (ms as f64 * sample_rate as f64 / 1000.0) as usize;
+        let padding = to_samples(settings.padding_ms);
+        let max_pause = to_samples(settings.max_pause_ms);
+
+        // without speech there is nothing to align the cuts on, so nothing is cut
+        if speech.is_empty() || (!settings.trim_silence && !settings.shorten_pauses) {
+            return TrimPlan {
+                sample_rate,
+                length,
+                kept: std::iter::once(0..length).collect(),
+                cursor: 0,
+            };
+        }
+
+        let mut kept: Vec<Range<usize>> = Vec::new();
+        for (start, end) in speech {
+            let range = start.saturating_sub(padding)..(end + padding).min(length);
+            match kept.last_mut() {
+                Some(last) if !settings.shorten_pauses || range.start <= last.end + max_pause => {
+                    last.end = range.end;
+                }
+                Some(last) => {
+                    // half of the longest pause allowed on each side of the cut
+                    last.end += max_pause / 2;
+                    kept.push(range.start - max_pause / 2..range.end);
+                }
+                None => kept.push(range),
+            }
+        }
+
+        if !settings.trim_silence {
+            if let Some(first) = kept.first_mut() {
+                first.start = 0;
+            }
+            if let Some(last) = kept.last_mut() {
+                last.end = length;
+            }
+        }
+
+        TrimPlan {
+            sample_rate,
+            length,
+            kept,
+            cursor: 0,
+        }
+    }
+
+    /// Position in the uploaded audio of a position of the recording
+    fn output_position(&self, position: usize) -> usize {
+        let mut output = 0;
+        for range in self.kept.iter() {
+            if position < range.start {
+                break;
+            }
+            output += position.min(range.end) - range.start;
+        }
+        output
+    }
+
+    fn to_ms(&self, position: usize) -> u64 {
+        (position as f64 * 1000.0 / self.sample_rate as f64).round() as u64
+    }
+
+    pub fn speech_segments(&self, speech: &[(usize, usize)]) -> Vec<SpeechSegment> {
+        speech
+            .iter()
+            .map(|(start, end)| SpeechSegment {
+                start_ms: self.to_ms(*start),
+                end_ms: self.to_ms(*end),
+                output_start_ms: self.to_ms(self.output_position(*start)),
+                output_end_ms: self.to_ms(self.output_position(*end)),
+            })
+            .collect()
+    }
+
+    pub fn output_duration_ms(&self) -> u64 {
+        self.to_ms(self.output_position(self.length))
+    }
+
+    pub fn removed_ranges(&self) -> Vec<RemovedRange> {
+        let mut removed = Vec::new();
+        let mut position = 0;
+        for range in self.kept.iter().chain(std::iter::once(&(self.length..self.length))) {
+            if range.start > position {
+                removed.push(RemovedRange {
+                    start_ms: self.to_ms(position),
+                    end_ms: self.to_ms(range.start),
+                });
+            }
+            position = range.end;
+        }
+        removed
+    }
+
+    /// Gain of the sample at `position`, None if it is cut.
+    /// Positions must be given in increasing order.
+    pub fn gain(&mut self, position: usize) -> Option<f64> {
+        let fade = (self.sample_rate as f64 * FADE_SECONDS) as usize;
+        while self.kept.get(self.cursor)?.end <= position {
+            self.cursor += 1;
+        }
+        let range = self.kept.get(self.cursor).filter(|range| range.contains(&position))?;
+
+        let mut gain: f64 = 1.0;
+        if range.start > 0 && position - range.start < fade {
+            gain = gain.min((position - range.start) as f64 / fade as f64);
+        }
+        if range.end < self.length && range.end - position <= fade {
+            gain = gain.min((range.end - position - 1) as f64 / fade as f64);
+        }
+        Some(gain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shortens_long_pauses_only() {
+        let settings = SilenceSettings {
+            trim_silence: true,
+            shorten_pauses: true,
+            max_pause_ms: 1000,
+            padding_ms: 0,
+            ..SilenceSettings::default()
+        };
+        // at 1kHz, speech from 2s to 3s, 3.5s to 4s and 10s to 11s, in a 12s recording
+        let speech = [(2000, 3000), (3500, 4000), (10000, 11000)];
+        let plan = TrimPlan::new(1000, 12000, &speech, &settings);
+
+        assert_eq!(plan.kept, vec![2000..4500, 9500..11000]);
+        assert_eq!(
+            plan.removed_ranges(),
+            vec![
+                RemovedRange { start_ms: 0, end_ms: 2000 },
+                RemovedRange { start_ms: 4500, end_ms: 9500 },
+                RemovedRange { start_ms: 11000, end_ms: 12000 },
+            ]
+        );
+
+        let segments = plan.speech_segments(&speech);
+        assert_eq!((segments[2].output_start_ms, segments[2].output_end_ms), (3000, 4000));
+        assert_eq!(plan.output_duration_ms(), 4000);
+    }
+}
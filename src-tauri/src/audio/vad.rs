@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+
+const FRAME_SECONDS: f64 = 0.02;
+// frames below this level are never speech, whatever the noise floor
+const ABSOLUTE_THRESHOLD_DB: f64 = -60.0;
+// the noise floor follows quieter frames quickly and louder ones slowly (about 10s)
+const FLOOR_FALL: f64 = 0.2;
+const FLOOR_RISE: f64 = 0.002;
+// consecutive speech frames needed to open a segment, silent frames needed to close it
+const ONSET_FRAMES: usize = 3;
+const HANGOVER_FRAMES: usize = 15;
+
+/// Speech found in the recording, in milliseconds from the start of the recording
+/// and from the start of the uploaded audio (they differ once silence is trimmed)
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpeechSegment {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub output_start_ms: u64,
+    pub output_end_ms: u64,
+}
+
+/// Energy based voice activity detection against an adaptive noise floor,
+/// fed one sample at a time and returning segments as sample ranges
+pub struct VoiceActivityDetector {
+    frame_length: usize,
+    threshold_db: f64,
+    frame_energy: f64,
+    frame_count: usize,
+    frames: usize,
+    noise_floor_db: f64,
+    // first frame of the current run of speech frames, and silent frames since the last one
+    speech_start: Option<usize>,
+    speech_frames: usize,
+    silent_frames: usize,
+    in_speech: bool,
+    segments: Vec<(usize, usize)>,
+}
+
+impl VoiceActivityDetector {
+    pub fn new(sample_rate: u32, threshold_db: f64) -> VoiceActivityDetector {
+        VoiceActivityDetector {
+            frame_length: (sample_rate as f64 * FRAME_SECONDS).round() as usize,
+            threshold_db,
+            frame_energy: 0.0,
+            frame_count: 0,
+            frames: 0,
+            noise_floor_db: ABSOLUTE_THRESHOLD_DB,
+            speech_start: None,
+            speech_frames: 0,
+            silent_frames: 0,
+            in_speech: false,
+            segments: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, sample: f64) {
+        self.frame_energy += sample * sample;
+        self.frame_count += 1;
+        if self.frame_count == self.frame_length {
+            let level_db = 10.0 * (self.frame_energy / self.frame_length as f64 + 1e-12).log10();
+            self.push_frame(level_db);
+            self.frame_energy = 0.0;
+            self.frame_count = 0;
+        }
+    }
+
+    fn push_frame(&mut self, level_db: f64) {
+        let is_speech =
+            level_db > ABSOLUTE_THRESHOLD_DB && level_db > self.noise_floor_db + self.threshold_db;
+
+        let rate = if level_db < self.noise_floor_db { FLOOR_FALL } else { FLOOR_RISE };
+        // speech is not noise, the floor only adapts to it very slowly
+        let rate = if is_speech { rate / 10.0 } else { rate };
+        self.noise_floor_db += (level_db - self.noise_floor_db) * rate;
+
+        if is_speech {
+            self.speech_start.get_or_insert(self.frames);
+            self.speech_frames += 1;
+            self.silent_frames = 0;
+            if !self.in_speech && self.speech_frames >= ONSET_FRAMES {
+                self.in_speech = true;
+            }
+        } else {
+            self.silent_frames += 1;
+            if !self.in_speech {
+                self.speech_start = None;
+                self.speech_frames = 0;
+            } else if self.silent_frames >= HANGOVER_FRAMES {
+                self.close_segment(self.frames + 1 - self.silent_frames);
+            }
+        }
+        self.frames += 1;
+    }
+
+    /// Ends the current segment before the frame `end`
+    fn close_segment(&mut self, end: usize) {
+        if let Some(start) = self.speech_start.take().filter(|start| *start < end) {
+            self.segments
+                .push((start * self.frame_length, end * self.frame_length));
+        }
+        self.in_speech = false;
+        self.speech_frames = 0;
+    }
+
+    /// Speech segments found since the last call, as sample ranges from the sample `start` of the signal.
+    /// Unless the signal ended, speech going on is cut at the current frame and goes on in the next call.
+    pub fn take_segments(&mut self, start: usize, ended: bool) -> Vec<(usize, usize)> {
+        if self.in_speech && ended {
+            self.close_segment(self.frames - self.silent_frames);
+        } else if self.in_speech {
+            let end = self.frames - self.silent_frames;
+            if let Some(speech_start) = self.speech_start.replace(self.frames) {
+                if speech_start < end {
+                    self.segments
+                        .push((speech_start * self.frame_length, end * self.frame_length));
+                }
+            }
+        }
+
+        self.segments
+            .drain(..)
+            .map(|(segment_start, end)| (segment_start.saturating_sub(start), end - start))
+            .filter(|(segment_start, end)| segment_start < end)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+
+    #[test]
+    fn finds_speech_between_noise() {
+        // 1s of faint noise, 1s of "voice", 2s of noise, 0.5s of voice, 1s of noise
+        let mut seed: u32 = 1;
+        let mut noise = move || {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            (seed as f64 / u32::MAX as f64 - 0.5) * 0.002
+        };
+        let voice = |i: usize| 0.2 * (2.0 * PI * 220.0 * i as f64 / 16000.0).sin();
+        let speech = [(16000, 32000), (64000, 72000)];
+
+        let mut detector = VoiceActivityDetector::new(16000, 10.0);
+        for i in 0..88000 {
+            let is_speech = speech.iter().any(|(start, end)| (*start..*end).contains(&i));
+            detector.push(if is_speech { voice(i) } else { 0.0 } + noise());
+        }
+
+        let segments = detector.take_segments(0, true);
+        assert_eq!(segments.len(), 2, "{:?}", segments);
+        for ((start, end), (expected_start, expected_end)) in segments.iter().zip(speech) {
+            // within one frame of the truth
+            assert!(start.abs_diff(expected_start) <= 320, "{:?}", segments);
+            assert!(end.abs_diff(expected_end) <= 320, "{:?}", segments);
+        }
+    }
+
+    #[test]
+    fn cuts_speech_across_calls() {
+        let voice = |i: usize| 0.2 * (2.0 * PI * 220.0 * i as f64 / 16000.0).sin();
+        let mut detector = VoiceActivityDetector::new(16000, 10.0);
+        let push = |detector: &mut VoiceActivityDetector, range: std::ops::Range<usize>| {
+            for i in range {
+                let is_speech = (16000..40000).contains(&i);
+                detector.push(if is_speech { voice(i) } else { 0.0 });
+            }
+        };
+
+        // the speech from 1s to 2.5s goes on over the call at 2s
+        push(&mut detector, 0..32000);
+        let first = detector.take_segments(0, false);
+        push(&mut detector, 32000..64000);
+        let second = detector.take_segments(32000, true);
+
+        assert_eq!(first.len(), 1, "{:?}", first);
+        assert!(first[0].0.abs_diff(16000) <= 320 && first[0].1 == 32000, "{:?}", first);
+        assert_eq!(second.len(), 1, "{:?}", second);
+        assert!(second[0].0 == 0 && second[0].1.abs_diff(8000) <= 320, "{:?}", second);
+    }
+}
@@ -0,0 +1,128 @@
+use std::{
+    fs::OpenOptions,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// Fixes the sizes in the header of a wav file that was not finalized, from the length of the file.
+/// A frame cut short at the end is dropped. Returns whether the file had to be fixed.
+pub fn repair_wav_header(filepath: &Path) -> io::Result<bool> {
+    let mut file = OpenOptions::new().read(true).write(true).open(filepath)?;
+    let file_length = file.metadata()?.len();
+
+    let mut riff_header = [0; 12];
+    file.read_exact(&mut riff_header)?;
+    if &riff_header[0..4] != b"RIFF" || &riff_header[8..12] != b"WAVE" {
+        return Err(invalid_data("Not a wav file"));
+    }
+
+    // the data chunk comes last, its size is the one left unwritten
+    let mut position = 12;
+    let mut block_align = None;
+    let data_size = loop {
+        if position + 8 > file_length {
+            return Err(invalid_data("No data chunk"));
+        }
+        let mut chunk_header = [0; 8];
+        file.seek(SeekFrom::Start(position))?;
+        file.read_exact(&mut chunk_header)?;
+        let chunk_size = read_u32(&chunk_header[4..8]);
+
+        match &chunk_header[0..4] {
+            b"data" => break chunk_size,
+            b"fmt " => {
+                let mut format = [0; 14];
+                file.read_exact(&mut format)?;
+                block_align = Some(u16::from_le_bytes([format[12], format[13]]).max(1));
+            }
+            _ => {}
+        }
+        // chunks are padded to an even size
+        position += 8 + chunk_size as u64 + (chunk_size & 1) as u64;
+    };
+    let block_align = block_align.ok_or_else(|| invalid_data("No format chunk"))? as u64;
+
+    let data_start = position + 8;
+    let data_length = (file_length - data_start) / block_align * block_align;
+    let data_length = u32::try_from(data_length).map_err(|_| invalid_data("Data too long"))?;
+    let riff_length = (data_start - 8) as u32 + data_length;
+    if data_size == data_length
+        && read_u32(&riff_header[4..8]) == riff_length
+        && data_start + data_length as u64 == file_length
+    {
+        return Ok(false);
+    }
+
+    file.set_len(data_start + data_length as u64)?;
+    file.seek(SeekFrom::Start(4))?;
+    file.write_all(&riff_length.to_le_bytes())?;
+    file.seek(SeekFrom::Start(position + 4))?;
+    file.write_all(&data_length.to_le_bytes())?;
+    file.flush()?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+
+    fn write_wav(filepath: &Path, samples: usize) {
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 48000,
+            bits_per_sample: 32,
+            sample_format: SampleFormat::Float,
+        };
+        let mut writer = WavWriter::create(filepath, spec).unwrap();
+        for i in 0..samples {
+            writer.write_sample(i as f32 / samples as f32).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    #[test]
+    fn finalized_file_is_left_alone() {
+        let filepath = std::env::temp_dir().join(format!(
+            "sniive_wav_repair_finalized_{}.wav",
+            std::process::id()
+        ));
+        write_wav(&filepath, 1000);
+
+        assert!(!repair_wav_header(&filepath).unwrap());
+        assert_eq!(WavReader::open(&filepath).unwrap().len(), 1000);
+        std::fs::remove_file(filepath).unwrap();
+    }
+
+    #[test]
+    fn unfinalized_file_keeps_its_whole_frames() {
+        let filepath = std::env::temp_dir().join(format!(
+            "sniive_wav_repair_unfinalized_{}.wav",
+            std::process::id()
+        ));
+        write_wav(&filepath, 1000);
+
+        // sizes as they are before the writer is finalized, with half a frame written after them
+        let mut bytes = std::fs::read(&filepath).unwrap();
+        bytes[4..8].copy_from_slice(&0u32.to_le_bytes());
+        let data_position = bytes.windows(4).position(|id| id == b"data").unwrap();
+        bytes[data_position + 4..data_position + 8].copy_from_slice(&0u32.to_le_bytes());
+        bytes.extend_from_slice(&[0; 4]);
+        std::fs::write(&filepath, bytes).unwrap();
+
+        assert!(repair_wav_header(&filepath).unwrap());
+        let mut reader = WavReader::open(&filepath).unwrap();
+        assert_eq!(reader.len(), 1000);
+        let last = reader.samples::<f32>().last().unwrap().unwrap();
+        assert_eq!(last, 999.0 / 1000.0);
+        std::fs::remove_file(filepath).unwrap();
+    }
+}
@@ -0,0 +1,287 @@
+use crabgrab::{prelude::CapturableDisplay, util::Rect};
+use serde::{Deserialize, Serialize};
+
+/// Identity of a display that does not depend on its resolution or position.
+/// `platform_id` and `hardware_id` are filled when the platform exposes them,
+/// the geometry is only kept for the fallback matcher.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DisplayIdentity {
+    // CGDirectDisplayID on macOS, GDI device name (\\.\DISPLAYn) on Windows
+    pub platform_id: Option<String>,
+    // EDID vendor/model/serial on macOS, monitor device interface path on Windows
+    pub hardware_id: Option<String>,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl DisplayIdentity {
+    pub fn of(display: &CapturableDisplay) -> DisplayIdentity {
+        let rect = display.rect();
+        let (platform_id, hardware_id) = platform_identity(&rect);
+        DisplayIdentity {
+            platform_id,
+            hardware_id,
+            x: rect.origin.x,
+            y: rect.origin.y,
+            width: rect.size.width,
+            height: rect.size.height,
+        }
+    }
+
+    /// Id sent to the frontend, stable as long as the platform can identify the display.
+    /// The platform id changes when the display is reconnected, the hardware id does not.
+    pub fn id(&self) -> isize {
+        let key = match (&self.hardware_id, &self.platform_id) {
+            (Some(hardware_id), _) => format!("hw:{}", hardware_id),
+            (None, Some(platform_id)) => format!("os:{}", platform_id),
+            (None, None) => format!("{}:{}:{}:{}", self.x, self.y, self.width, self.height),
+        };
+
+        // keep the id within the range of a javascript number
+        (fnv1a(key.as_bytes()) & 0x7FFF_FFFF) as isize
+    }
+
+    /// How well `self` describes `other`, 0 meaning it is not the same display
+    fn match_score(&self, other: &DisplayIdentity) -> u32 {
+        let mut score = 0;
+        match (&self.hardware_id, &other.hardware_id) {
+            (Some(a), Some(b)) if a == b => score += 8,
+            // both monitors report their hardware and it differs
+            (Some(_), Some(_)) => return 0,
+            _ => {}
+        }
+        if self.platform_id.is_some() && self.platform_id == other.platform_id {
+            score += 4;
+        }
+        if self.width == other.width && self.height == other.height {
+            score += 2;
+        }
+        if self.x == other.x && self.y == other.y {
+            score += 1;
+        }
+        score
+    }
+
+    fn distance(&self, other: &DisplayIdentity) -> f64 {
+        (self.x - other.x).hypot(self.y - other.y)
+    }
+}
+
+pub fn display_id(display: &CapturableDisplay) -> isize {
+    DisplayIdentity::of(display).id()
+}
+
+/// Index of the candidate matching `identity`, first by id, then by the best identity score.
+/// Identical monitors score the same, the one closest to where the display was wins.
+fn best_match(identity: &DisplayIdentity, candidates: &[DisplayIdentity]) -> Option<usize> {
+    let id = identity.id();
+    if let Some(index) = candidates.iter().position(|candidate| candidate.id() == id) {
+        return Some(index);
+    }
+
+    let mut best: Option<(u32, f64, usize)> = None;
+    for (index, candidate) in candidates.iter().enumerate() {
+        let score = identity.match_score(candidate);
+        let distance = identity.distance(candidate);
+        let better = match best {
+            Some((best_score, best_distance, _)) => {
+                score > best_score || (score == best_score && distance < best_distance)
+            }
+            None => score > 0,
+        };
+        if better {
+            best = Some((score, distance, index));
+        }
+    }
+    best.map(|(_, _, index)| index)
+}
+
+/// Finds the display matching `identity` among the current ones
+pub fn find_display(
+    displays: impl Iterator<Item = CapturableDisplay>,
+    identity: &DisplayIdentity,
+) -> Option<CapturableDisplay> {
+    let mut displays: Vec<CapturableDisplay> = displays.collect();
+    let candidates: Vec<DisplayIdentity> = displays.iter().map(DisplayIdentity::of).collect();
+    let index = best_match(identity, &candidates)?;
+    Some(displays.swap_remove(index))
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(target_os = "macos")]
+fn platform_identity(rect: &Rect) -> (Option<String>, Option<String>) {
+    use core_graphics::display::{CGDisplay, CGPoint, CGRect, CGSize};
+
+    let bounds = CGRect::new(
+        &CGPoint::new(rect.origin.x, rect.origin.y),
+        &CGSize::new(rect.size.width, rect.size.height),
+    );
+    let ids = match CGDisplay::displays_with_rect(bounds, 16) {
+        Ok((ids, _)) => ids,
+        Err(_) => return (None, None),
+    };
+
+    // mirrored displays share a rect, prefer the one whose origin matches exactly
+    let id = match ids
+        .iter()
+        .find(|id| {
+            let display_bounds = CGDisplay::new(**id).bounds();
+            display_bounds.origin.x == rect.origin.x && display_bounds.origin.y == rect.origin.y
+        })
+        .or(ids.first())
+    {
+        Some(id) => *id,
+        None => return (None, None),
+    };
+
+    let display = CGDisplay::new(id);
+    let (vendor, model, serial) = (display.vendor_number(), display.model_number(), display.serial_number());
+    let hardware_id = if vendor == 0 && model == 0 {
+        None
+    } else if serial == 0 {
+        // no serial in the EDID, identical monitors are told apart by their unit number
+        Some(format!("{:04x}-{:04x}-unit{}", vendor, model, display.unit_number()))
+    } else {
+        Some(format!("{:04x}-{:04x}-{:08x}", vendor, model, serial))
+    };
+
+    (Some(id.to_string()), hardware_id)
+}
+
+#[cfg(target_os = "windows")]
+fn platform_identity(rect: &Rect) -> (Option<String>, Option<String>) {
+    use windows::{
+        core::PCWSTR,
+        Win32::{
+            Foundation::POINT,
+            Graphics::Gdi::{
+                EnumDisplayDevicesW, GetMonitorInfoW, MonitorFromPoint, DISPLAY_DEVICEW,
+                MONITORINFO, MONITORINFOEXW, MONITOR_DEFAULTTONULL,
+            },
+            UI::WindowsAndMessaging::EDD_GET_DEVICE_INTERFACE_NAME,
+        },
+    };
+
+    fn from_wide(wide: &[u16]) -> String {
+        let len = wide.iter().position(|c| *c == 0).unwrap_or(wide.len());
+        String::from_utf16_lossy(&wide[..len])
+    }
+
+    let center = POINT {
+        x: (rect.origin.x + rect.size.width / 2.0) as i32,
+        y: (rect.origin.y + rect.size.height / 2.0) as i32,
+    };
+    let monitor = unsafe { MonitorFromPoint(center, MONITOR_DEFAULTTONULL) };
+    if monitor.is_invalid() {
+        return (None, None);
+    }
+
+    let mut info = MONITORINFOEXW::default();
+    info.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
+    if !unsafe { GetMonitorInfoW(monitor, &mut info as *mut MONITORINFOEXW as *mut MONITORINFO) }.as_bool() {
+        return (None, None);
+    }
+    let device_name = from_wide(&info.szDevice);
+
+    // the monitor interface path embeds the EDID manufacturer and product code and the connector instance
+    let mut device = DISPLAY_DEVICEW {
+        cb: std::mem::size_of::<DISPLAY_DEVICEW>() as u32,
+        ..Default::default()
+    };
+    let hardware_id = if unsafe {
+        EnumDisplayDevicesW(
+            PCWSTR(info.szDevice.as_ptr()),
+            0,
+            &mut device,
+            EDD_GET_DEVICE_INTERFACE_NAME,
+        )
+    }
+    .as_bool()
+    {
+        Some(from_wide(&device.DeviceID)).filter(|id| !id.is_empty())
+    } else {
+        None
+    };
+
+    (Some(device_name), hardware_id)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn platform_identity(_rect: &Rect) -> (Option<String>, Option<String>) {
+    (None, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(hardware_id: Option<&str>, platform_id: Option<&str>, x: f64) -> DisplayIdentity {
+        DisplayIdentity {
+            platform_id: platform_id.map(str::to_string),
+            hardware_id: hardware_id.map(str::to_string),
+            x,
+            y: 0.0,
+            width: 1920.0,
+            height: 1080.0,
+        }
+    }
+
+    #[test]
+    fn id_survives_a_reconnection() {
+        let before = identity(Some("10ac-a0c4-1234"), Some("2"), 0.0);
+        let after = identity(Some("10ac-a0c4-1234"), Some("5"), 1920.0);
+        assert_eq!(before.id(), after.id());
+        assert_ne!(
+            identity(None, Some("2"), 0.0).id(),
+            identity(None, Some("5"), 0.0).id()
+        );
+    }
+
+    #[test]
+    fn hardware_id_decides_the_match() {
+        let selected = identity(Some("monitor-b"), Some("1"), 0.0);
+        let candidates = [
+            identity(Some("monitor-a"), Some("1"), 0.0),
+            identity(Some("monitor-b"), Some("3"), 1920.0),
+        ];
+        assert_eq!(best_match(&selected, &candidates), Some(1));
+    }
+
+    #[test]
+    fn identical_monitors_are_told_apart_by_position() {
+        // the platform gives nothing, the displays only differ by where they are
+        let selected = identity(None, None, 1920.0);
+        let moved = DisplayIdentity {
+            y: 10.0,
+            ..identity(None, None, 1900.0)
+        };
+        let candidates = [
+            identity(None, None, 0.0),
+            moved,
+            identity(None, None, 3840.0),
+        ];
+        assert_eq!(best_match(&selected, &candidates), Some(1));
+
+        let other_size = DisplayIdentity {
+            width: 1280.0,
+            ..identity(None, None, 1920.0)
+        };
+        assert_eq!(best_match(&selected, &[other_size]), Some(0));
+        // monitors that report different hardware are never the same
+        let unrelated = [identity(Some("b"), None, 0.0)];
+        assert_eq!(
+            best_match(&identity(Some("a"), None, 0.0), &unrelated),
+            None
+        );
+    }
+}
@@ -0,0 +1,3 @@
+pub mod display_identity;
+pub mod thumbnail_cache;
+pub mod surface_liveness;
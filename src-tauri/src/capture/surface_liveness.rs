@@ -0,0 +1,130 @@
+use std::{sync::atomic::Ordering, time::Duration};
+
+use crabgrab::prelude::{CapturableContent, CapturableContentFilter, CapturableWindow};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+#[cfg(target_os = "windows")]
+use crabgrab::platform::windows::WindowsCapturableWindowExt;
+#[cfg(target_os = "macos")]
+use crabgrab::platform::macos::MacosCapturableWindowExt;
+
+use crate::app::{
+    app_state::{AppState, CapturableSurface},
+    cmd_pause_recording::pause,
+    cmd_select_surface_start::SurfaceType,
+};
+
+use super::display_identity::display_id;
+
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub enum SurfaceLostReason {
+    Closed,        // The window was closed or the display unplugged
+    CaptureFailed, // The surface still exists but cannot be captured anymore
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SurfaceLostEvent {
+    pub surface_type: SurfaceType,
+    pub title: String,
+    pub reason: SurfaceLostReason,
+}
+
+fn window_id(window: &CapturableWindow) -> isize {
+    #[cfg(target_os = "windows")]
+    return window.get_window_handle().0;
+    #[cfg(target_os = "macos")]
+    return window.get_window_id() as isize;
+}
+
+pub async fn is_surface_alive(surface: &CapturableSurface) -> bool {
+    let filter = CapturableContentFilter::EVERYTHING_NORMAL;
+    let content = match CapturableContent::new(filter).await {
+        Ok(content) => content,
+        // not being able to list the content does not mean the surface is gone
+        Err(_) => return true,
+    };
+
+    match surface {
+        CapturableSurface::CapturableWindow(window) => {
+            let id = window_id(window);
+            content.windows().any(|window| window_id(&window) == id)
+        }
+        CapturableSurface::CapturableDisplay(display) => {
+            let id = display_id(display);
+            content.displays().any(|display| display_id(&display) == id)
+        }
+    }
+}
+
+/// Pauses the recording and tells the frontend that a replacement surface is needed
+pub async fn mark_surface_lost(
+    app_handle: &AppHandle,
+    reason: SurfaceLostReason,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let state = app_handle
+        .try_state::<AppState>()
+        .ok_or("Failed to get AppState")?;
+    let lost_surface = {
+        let mut capturable_surface = state.capturable_surface.lock().await;
+        // without a selected surface there is nothing to lose
+        if capturable_surface.is_none() || state.surface_lost.swap(true, Ordering::Relaxed) {
+            return Ok(());
+        }
+        capturable_surface.take()
+    };
+
+    // the audio stops with the inputs, a pause of the user is left to the user.
+    // a failed pause still asks for a replacement, without it the recording would be stuck
+    match pause(app_handle).await {
+        Ok(true) => state.paused_by_surface_loss.store(true, Ordering::Relaxed),
+        Ok(false) => {}
+        Err(e) => eprintln!("Failed to pause the recording: {}", e),
+    }
+
+    let event = match lost_surface {
+        Some(CapturableSurface::CapturableWindow(window)) => SurfaceLostEvent {
+            surface_type: SurfaceType::Window,
+            title: window.title(),
+            reason,
+        },
+        Some(CapturableSurface::CapturableDisplay(_)) => SurfaceLostEvent {
+            surface_type: SurfaceType::Display,
+            title: "Monitor".to_string(),
+            reason,
+        },
+        None => return Ok(()),
+    };
+
+    if let Err(e) = app_handle.emit("surface-lost", event) {
+        eprintln!("Failed to emit surface-lost: {}", e);
+    }
+    Ok(())
+}
+
+/// Periodically checks that the selected surface still exists while recording
+pub async fn surface_watchdog(app_handle: &AppHandle) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    loop {
+        tokio::time::sleep(WATCHDOG_INTERVAL).await;
+
+        let state = app_handle
+            .try_state::<AppState>()
+            .ok_or("Failed to get AppState")?;
+        if !state.can_run_input.load(Ordering::Relaxed) || state.surface_lost.load(Ordering::Relaxed) {
+            continue;
+        }
+
+        // the lock is not held while listing the content, the input controller needs it
+        let surface = state.capturable_surface.lock().await.clone();
+        if let Some(surface) = surface {
+            if !is_surface_alive(&surface).await {
+                // the next check tries again, the recording goes on either way
+                if let Err(e) = mark_surface_lost(app_handle, SurfaceLostReason::Closed).await {
+                    eprintln!("Failed to mark the surface as lost: {}", e);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+// long enough to reopen the picker, short enough for thumbnails to look current
+const THUMBNAIL_TTL: Duration = Duration::from_secs(10);
+
+struct CachedThumbnail {
+    created_at: Instant,
+    thumbnail: String,
+}
+
+/// Base64 jpeg thumbnails keyed by surface id and size
+#[derive(Default)]
+pub struct ThumbnailCache {
+    entries: HashMap<(isize, u32), CachedThumbnail>,
+}
+
+impl ThumbnailCache {
+    pub fn get(&mut self, id: isize, size: u32) -> Option<String> {
+        self.entries
+            .retain(|_, entry| entry.created_at.elapsed() < THUMBNAIL_TTL);
+        self.entries
+            .get(&(id, size))
+            .map(|entry| entry.thumbnail.clone())
+    }
+
+    pub fn insert(&mut self, id: isize, size: u32, thumbnail: String) {
+        self.entries.insert(
+            (id, size),
+            CachedThumbnail {
+                created_at: Instant::now(),
+                thumbnail,
+            },
+        );
+    }
+}
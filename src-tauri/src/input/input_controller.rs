@@ -16,6 +16,7 @@ use crabgrab::platform::macos::MacosCapturableWindowExt;
 
 use crate::{
     app::app_state::{AppState, CapturableSurface},
+    capture::surface_liveness::{is_surface_alive, mark_surface_lost, SurfaceLostReason},
     utils::is_in,
 };
 
@@ -32,15 +33,23 @@ pub async fn input_controller(
     async_transmitter: Sender<ToUploadEvent>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let mut state_machine = StateMachine::new();
+    let mut capture_failures: u32 = 0;
 
     while let Some(input_event) = async_receiver.recv().await {
         let state = app_handle
             .try_state::<AppState>()
             .ok_or("Failed to get AppState")?;
+
+        // the recording is paused until the user picks a replacement surface
+        if state.surface_lost.load(std::sync::atomic::Ordering::Relaxed) {
+            continue;
+        }
+
         let capturable_surface_lock = state.capturable_surface.lock().await;
-        let capturable_surface = capturable_surface_lock
-            .as_ref()
-            .ok_or("No capturable surface")?;
+        let capturable_surface = match capturable_surface_lock.as_ref() {
+            Some(capturable_surface) => capturable_surface,
+            None => continue,
+        };
 
         let display_rect = match capturable_surface {
             CapturableSurface::CapturableWindow(window) => window.rect(),
@@ -113,17 +122,35 @@ pub async fn input_controller(
                     CapturePixelFormat::Bgra8888,
                 ))
             }
-        }?;
+        };
+        let capturable_surface = capturable_surface.clone();
+        drop(capturable_surface_lock);
+
+        let capture_config = match capture_config {
+            Ok(capture_config) => capture_config,
+            Err(_) => {
+                handle_capture_failure(app_handle, &capturable_surface, &mut capture_failures).await;
+                continue;
+            }
+        };
 
         if let Some(events) = state_machine.update(input_event, display_rect) {
-            let capture_token_lock = state.capture_token.lock().await;
-            let capture_token = capture_token_lock.as_ref().ok_or("No capture token")?;
+            let capture_token = *state.capture_token.lock().await;
+            let capture_token = capture_token.ok_or("No capture token")?;
 
-            let screenshot = timeout(
+            let screenshot = match timeout(
                 Duration::from_secs(1),
-                take_screenshot(*capture_token, capture_config),
+                take_screenshot(capture_token, capture_config),
             )
-            .await??;
+            .await
+            {
+                Ok(Ok(screenshot)) => screenshot,
+                _ => {
+                    handle_capture_failure(app_handle, &capturable_surface, &mut capture_failures).await;
+                    continue;
+                }
+            };
+            capture_failures = 0;
 
             async_transmitter
                 .send(ToUploadEvent { events, screenshot })
@@ -132,3 +159,30 @@ pub async fn input_controller(
     }
     Ok(())
 }
+
+// consecutive failed captures of a surface that still exists before giving up on it
+const MAX_CAPTURE_FAILURES: u32 = 3;
+
+/// A failed capture pauses the recording if the surface is gone, otherwise the step is skipped.
+/// A failure to mark the surface as lost is logged, the inputs go on being read
+async fn handle_capture_failure(
+    app_handle: &AppHandle,
+    capturable_surface: &CapturableSurface,
+    capture_failures: &mut u32,
+) {
+    let reason = if !is_surface_alive(capturable_surface).await {
+        SurfaceLostReason::Closed
+    } else {
+        *capture_failures += 1;
+        if *capture_failures < MAX_CAPTURE_FAILURES {
+            eprintln!("Failed to capture the surface, skipping step");
+            return;
+        }
+        SurfaceLostReason::CaptureFailed
+    };
+
+    *capture_failures = 0;
+    if let Err(e) = mark_surface_lost(app_handle, reason).await {
+        eprintln!("Failed to mark the surface as lost: {}", e);
+    }
+}
@@ -6,6 +6,12 @@ use crate::{app::app_state::AppState, utils};
 
 use super::state_machine::{InputEvent, MousePosition};
 
+/// Inputs are only taken while recording, a pause leaves them out
+fn is_recording(state: &AppState) -> bool {
+    state.can_run_input.load(std::sync::atomic::Ordering::Relaxed)
+        && !state.recording_paused.load(std::sync::atomic::Ordering::Relaxed)
+}
+
 pub fn input_loop(app_handle: &AppHandle, async_sender: Sender<InputEvent>) {
     // Clone the app_handle to avoid lifetime issues
     let app_handle = app_handle.clone();
@@ -25,19 +31,26 @@ pub fn input_loop(app_handle: &AppHandle, async_sender: Sender<InputEvent>) {
             _ => {}
         }
 
+        // every key event makes a sound the audio conversion can duck, even when it is not a step
+        if let rdev::EventType::KeyPress(_) | rdev::EventType::KeyRelease(_) = event.event_type {
+            if let Some(state) = app_handle.try_state::<AppState>() {
+                if is_recording(&state) {
+                    state.keystroke_times.blocking_lock().push(state.recording_clock.now());
+                }
+            }
+        }
+
         match event.event_type {
             rdev::EventType::MouseMove { x, y } => {
                 mouse_position = MousePosition { x, y };
             }
             _ => {
                 if let Some(state) = app_handle.try_state::<AppState>() {
-                    if state
-                        .can_run_input
-                        .load(std::sync::atomic::Ordering::Relaxed)
-                    {
+                    if is_recording(&state) {
                         let input_event = InputEvent {
                             event: event.event_type,
                             mouse_position,
+                            time: state.recording_clock.now(),
                         };
                         async_sender.blocking_send(input_event).map_err(|_| utils::show_error_dialog(&app_handle, "Not enough time to process inputs")).unwrap();
                     }
@@ -11,6 +11,8 @@ pub struct MousePosition {
 pub struct InputEvent {
     pub event: rdev::EventType,
     pub mouse_position: MousePosition,
+    // on the recording clock, as the audio is
+    pub time: SystemTime,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -74,7 +76,7 @@ impl StateMachine {
         }
     }
 
-    fn flush_buffer(&mut self) -> Option<StateMachineResult> {
+    fn flush_buffer(&mut self, time: SystemTime) -> Option<StateMachineResult> {
         if self.buffer.is_empty() {
             return None;
         }
@@ -83,11 +85,11 @@ impl StateMachine {
         let buffer = self.buffer.clone();
         let buffer_start_time = self.buffer_start_time;
         self.buffer = Vec::new();
-        self.buffer_start_time = SystemTime::now();
+        self.buffer_start_time = time;
         return Some(StateMachineResult {
             events: buffer,
             start_time: buffer_start_time,
-            end_time: SystemTime::now(),
+            end_time: time,
         });
     }
 
@@ -100,7 +102,7 @@ impl StateMachine {
             rdev::EventType::KeyPress(key) => {
                 if is_special(&key) && self.trigger.is_none() {
                     self.trigger = Some(key);
-                    let res = self.flush_buffer();
+                    let res = self.flush_buffer(event.time);
                     self.buffer
                         .push(Event::KeyboardEvent(KeyboardEvent { key }));
                     self.state = StateMachineState::Special;
@@ -112,7 +114,7 @@ impl StateMachine {
                 }
             }
             rdev::EventType::ButtonPress(button) => {
-                let res = self.flush_buffer();
+                let res = self.flush_buffer(event.time);
                 self.buffer.push(Event::MouseEvent(MouseEvent {
                     button,
                     mouse_position: event.mouse_position,
@@ -135,7 +137,7 @@ impl StateMachine {
         match event.event {
             rdev::EventType::ButtonRelease(_) => {
                 //self.buffer.push(event);
-                let res = self.flush_buffer();
+                let res = self.flush_buffer(event.time);
                 self.state = StateMachineState::Normal;
                 return res;
             }
@@ -180,7 +182,7 @@ impl StateMachine {
             rdev::EventType::KeyRelease(key) => {
                 if Some(key) == self.trigger {
                     self.trigger = None;
-                    let res = self.flush_buffer();
+                    let res = self.flush_buffer(event.time);
                     self.state = StateMachineState::Normal;
                     return res;
                 } else {
@@ -1,7 +1,8 @@
 use app::{
-    app_state::AppState, cmd_cancel_recording::cancel_recording, cmd_finish_recording::finish_recording, cmd_get_locale::get_locale, cmd_is_auth::is_auth, cmd_select_audio::select_audio, cmd_select_surface::select_surface, cmd_set_auth::set_auth, cmd_start_input::start_input, cmd_stop_input::stop_input
+    app_settings::AppSettings, app_state::AppState, cmd_audio_settings::{get_audio_settings, set_audio_settings}, cmd_audio_configs::{list_audio_configs, set_audio_config}, cmd_preview_audio_processing::preview_audio_processing, cmd_test_audio_device::test_audio_device, cmd_cancel_recording::cancel_recording, cmd_interrupted_recordings::{list_interrupted_recordings, notify_interrupted_recordings, recover_recording, repair_interrupted_recordings}, cmd_finish_recording::finish_recording, cmd_get_locale::get_locale, cmd_is_auth::is_auth, cmd_list_audio_devices::list_audio_devices, cmd_list_loopback_devices::list_loopback_devices, cmd_list_surfaces::list_surfaces, cmd_restored_selection::{clear_restored_selection, get_restored_selection, restore_selection}, cmd_select_audio::select_audio, cmd_select_surface::select_surface, cmd_set_audio_device::set_audio_device, cmd_set_auth::set_auth, cmd_set_surface::set_surface, cmd_start_input::start_input, cmd_stop_input::stop_input, cmd_pause_recording::pause_recording, cmd_resume_recording::resume_recording
 };
-use audio::audio_controller::audio_controller;
+use audio::{alignment::RecordingClock, audio_controller::{audio_controller, AudioCommand}};
+use capture::{surface_liveness::surface_watchdog, thumbnail_cache::ThumbnailCache};
 use input::{
     input_controller::{input_controller, ToUploadEvent},
     input_loop::input_loop,
@@ -13,7 +14,7 @@ use std::sync::atomic::AtomicBool;
 use tauri::{async_runtime, Listener, Manager};
 use tauri_plugin_updater::UpdaterExt;
 use tauri_plugin_fs::FsExt;
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{mpsc, watch, Mutex};
 use upload::upload_controller::upload_controller;
 use utils::match_deep_link;
 use serde_json::Value;
@@ -21,6 +22,7 @@ use tauri_plugin_cli::CliExt;
 
 mod app;
 mod audio;
+mod capture;
 mod input;
 mod upload;
 mod utils;
@@ -29,7 +31,7 @@ mod utils;
 pub fn run() {
     let (async_ic2uc_tx, async_ic2uc_rx) = mpsc::channel::<ToUploadEvent>(8);
     let (async_il2ic_tx, async_il2ic_rx) = mpsc::channel::<InputEvent>(8);
-    let (async_ta2ac_tx, async_ta2ac_rx) = mpsc::channel::<bool>(1);
+    let (async_ta2ac_tx, async_ta2ac_rx) = mpsc::channel::<AudioCommand>(1);
     static APP_HANDLE: StaticCell<tauri::AppHandle> = StaticCell::new();
 
     tauri::Builder::default()
@@ -44,26 +46,60 @@ pub fn run() {
         .manage(AppState {
             //async_nx2ta_tx: Mutex::new(async_nx2ta_tx),
             can_run_input: AtomicBool::new(false),
+            recording_paused: AtomicBool::new(false),
+            surface_lost: AtomicBool::new(false),
+            paused_by_surface_loss: AtomicBool::new(false),
+            steps_only: AtomicBool::new(false),
             capturable_surface: Mutex::new(None),
             capture_token: Mutex::new(None),
+            thumbnail_cache: Mutex::new(ThumbnailCache::default()),
 
-            can_run_audio: Mutex::new(async_ta2ac_tx),
+            audio_commands: Mutex::new(async_ta2ac_tx),
             audio_device: Mutex::new(None),
+            audio_capture_report: Mutex::new(None),
+            audio_segments: Mutex::new(Vec::new()),
+            failed_audio_segments: Mutex::new(Vec::new()),
+            audio_pending: watch::channel(false).0,
 
             auth: Mutex::new(None),
+            recording_session: Mutex::new(None),
+            session_journal: Mutex::new(None),
             recording_start_time: Mutex::new(None),
             recording_end_time: Mutex::new(None),
+            recording_pauses: Mutex::new(Vec::new()),
+            keystroke_times: Mutex::new(Vec::new()),
+            step_times: Mutex::new(Vec::new()),
+            recording_clock: RecordingClock::default(),
+
+            settings: Mutex::new(AppSettings::default()),
         })
         .invoke_handler(tauri::generate_handler![
             start_input,
             stop_input,
+            pause_recording,
+            resume_recording,
             select_surface,
             select_audio,
             set_auth,
             is_auth,
             get_locale,
             finish_recording,
-            cancel_recording
+            cancel_recording,
+            list_interrupted_recordings,
+            recover_recording,
+            get_restored_selection,
+            clear_restored_selection,
+            list_surfaces,
+            set_surface,
+            list_audio_devices,
+            set_audio_device,
+            list_loopback_devices,
+            get_audio_settings,
+            set_audio_settings,
+            list_audio_configs,
+            set_audio_config,
+            preview_audio_processing,
+            test_audio_device
         ])
         .setup(|app| {
             let app_handle: &'static tauri::AppHandle = APP_HANDLE.init(app.handle().clone());
@@ -72,6 +108,8 @@ pub fn run() {
             });
             app_cli_handler(app_handle);
             app_files_handler(app_handle);
+            app_settings_handler(app_handle);
+            app_recovery_handler(app_handle);
 
             if app_handle
                 .plugin(tauri_plugin_updater::Builder::new().build())
@@ -120,6 +158,12 @@ pub fn run() {
                 })
             });
 
+            async_runtime::spawn(async move {
+                surface_watchdog(app_handle)
+                .await
+                .map_err(|e| eprintln!("Surface watchdog error: {:?}", e))
+            });
+
             async_runtime::spawn_blocking(move || {
                 input_loop(app_handle, async_il2ic_tx);
             });
@@ -208,6 +252,31 @@ async fn app_update_handler(
     Ok(())
 }
 
+fn app_settings_handler(app_handle: &'static tauri::AppHandle) {
+    let state = app_handle
+        .try_state::<AppState>()
+        .ok_or("Failed to get AppState")
+        .expect("Failed to get AppState");
+    *state.settings.blocking_lock() = AppSettings::load(app_handle);
+
+    async_runtime::spawn(async move {
+        if let Err(e) = restore_selection(app_handle).await {
+            eprintln!("Failed to restore the last selection: {}", e);
+        }
+    });
+}
+
+fn app_recovery_handler(app_handle: &'static tauri::AppHandle) {
+    async_runtime::spawn(async move {
+        if let Err(e) = repair_interrupted_recordings(app_handle).await {
+            eprintln!("Failed to repair the interrupted recordings: {}", e);
+        }
+        if let Err(e) = notify_interrupted_recordings(app_handle).await {
+            eprintln!("Failed to list the interrupted recordings: {}", e);
+        }
+    });
+}
+
 fn app_files_handler(app_handle: &tauri::AppHandle) {
     let scope = app_handle.fs_scope();
     let tmp_path = app_handle.path().app_cache_dir().expect("Failed to get cache dir");
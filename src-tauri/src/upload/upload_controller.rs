@@ -1,13 +1,30 @@
 use crabgrab::prelude::{FrameBitmap, VideoFrameBitmap, VideoFrameBitmapError};
-use tauri::AppHandle;
+use tauri::{AppHandle, Manager};
 use tokio::sync::mpsc::Receiver;
 
 use crate::{
+    app::{
+        app_state::{AppState, StepTime},
+        recording_session::RecordingSession,
+        session_journal::update_journal,
+    },
     input::input_controller::ToUploadEvent,
     upload::serialize_result::serialize_result,
     utils,
 };
 
+/// Writes the payload of a step in the session, returns its index
+async fn save_step(session: &RecordingSession, result_string: &str) -> Result<usize, String> {
+    let step = session.next_step()?;
+    tokio::fs::write(
+        session.steps_dir().join(format!("{}.json", step)),
+        result_string,
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+    Ok(step)
+}
+
 pub async fn upload_controller(
     app_handle: &AppHandle,
     mut async_receiver: Receiver<ToUploadEvent>,
@@ -23,26 +40,77 @@ pub async fn upload_controller(
             }
         };
 
-        let image_base64 =
-            utils::make_base64_jpeg_from_bitmap(&image_bitmap_bgra8888)?;
+        let jpeg_data = utils::make_jpeg_from_bitmap(&image_bitmap_bgra8888, None)?;
+        let image_base64 = rbase64::encode(&jpeg_data);
 
         let result_string = serialize_result(&events, &image_base64)?;
-        let upload_link = utils::get_upload_link(app_handle, "json").await.map_err(|_| utils::show_error_dialog(app_handle, "Trouble connecting to client"))?;
-
-        let client = reqwest::Client::new();
-        let res = client
-            .put(&upload_link)
-            .header("Content-Type", "application/json")
-            .header("x-ms-blob-type", "BlockBlob")
-            .body(result_string)
-            .send()
-            .await
-            .map_err(|_| utils::show_error_dialog(app_handle, "Failed to parse response"))?;
-
-        if !res.status().is_success() {
-            eprintln!("Failed to upload data: {:?}", res);
+
+        // the step stays in the session until the recording is finished or cancelled
+        let state = app_handle
+            .try_state::<AppState>()
+            .ok_or("Failed to get AppState")?;
+        let session = state.recording_session.lock().await.clone();
+        // the screenshot is in the payload, a step that cannot be written is still uploaded
+        let step = match session {
+            Some(session) => match save_step(&session, &result_string).await {
+                Ok(step) => Some(step),
+                Err(e) => {
+                    eprintln!("Failed to write the step: {}", e);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        // the audio of the step is found from its times when the recording is finished
+        let step_times = {
+            let mut step_times = state.step_times.lock().await;
+            step_times.push(StepTime {
+                start: events.start_time,
+                end: events.end_time,
+            });
+            step_times.clone()
+        };
+        update_journal(app_handle, |journal| journal.step_times = step_times).await;
+
+        // a step that did not reach the server is uploaded again when the recording is recovered
+        match upload_step(app_handle, result_string).await {
+            Ok(true) => {
+                if let Some(step) = step {
+                    update_journal(app_handle, |journal| {
+                        journal.uploaded_steps.insert(step);
+                    })
+                    .await;
+                }
+            }
+            Ok(false) => eprintln!("Failed to upload the step"),
+            Err(e) => eprintln!("Failed to upload the step: {}", e),
         }
     }
 
     Ok(())
 }
+
+/// Uploads the payload of a step, returns whether it was accepted
+pub async fn upload_step(
+    app_handle: &AppHandle,
+    result_string: String,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    let upload_link = utils::get_upload_link(app_handle, "json", "application/json", None).await.map_err(|_| utils::show_error_dialog(app_handle, "Trouble connecting to client"))?;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .put(&upload_link)
+        .header("Content-Type", "application/json")
+        .header("x-ms-blob-type", "BlockBlob")
+        .body(result_string)
+        .send()
+        .await
+        .map_err(|_| utils::show_error_dialog(app_handle, "Failed to parse response"))?;
+
+    if !res.status().is_success() {
+        eprintln!("Failed to upload data: {:?}", res);
+        return Ok(false);
+    }
+    Ok(true)
+}
@@ -1,11 +1,7 @@
-use std::{
-    fs::File,
-    io::BufWriter,
-    sync::{Arc, Mutex},
-};
-use cpal::{FromSample, Sample};
+use std::path::PathBuf;
+
 use crabgrab::{
-    prelude::{BitmapDataBgra8x4, CapturableDisplay, FrameBitmapBgraUnorm8x4},
+    prelude::{BitmapDataBgra8x4, FrameBitmapBgraUnorm8x4},
     util::Rect,
 };
 use tauri::{AppHandle, Event, Listener, Manager, WebviewWindow};
@@ -38,24 +34,6 @@ pub async fn wait_for_event(window: &WebviewWindow, event_name: String) -> Optio
     }
 }
 
-pub fn display_id(display: &CapturableDisplay) -> isize {
-    let rect = display.rect();
-    let xored = ((rect.origin.x as i64) << 0)
-        ^ ((rect.origin.y as i64) << 16)
-        ^ ((rect.size.width as i64) << 32)
-        ^ ((rect.size.height as i64) << 48);
-
-    // isize may be 32 or 64 bits, if it's 32 bits, we need to split the xored into two i32s and combine them
-    if std::mem::size_of::<isize>() == 4 {
-        let xored1 = (xored & 0xFFFFFFFF) as i32;
-        let xored2 = ((xored >> 32) & 0xFFFFFFFF) as i32;
-        return (xored1 ^ xored2) as isize;
-    }
-
-    xored as isize
-}
-
-
 fn flatten(vec: &[[u8; 4]]) -> Vec<u8> {
     let total_size = vec.len() * 4;
     let mut flattened = Vec::with_capacity(total_size);
@@ -69,38 +47,49 @@ fn flatten(vec: &[[u8; 4]]) -> Vec<u8> {
     flattened
 }
 
-pub fn make_base64_jpeg_from_bitmap<Data: BitmapDataBgra8x4>(
+pub fn make_jpeg_from_bitmap<Data: BitmapDataBgra8x4>(
     bitmap: &FrameBitmapBgraUnorm8x4<Data>,
-) -> Result<String, String> {
+    max_size: Option<u32>,
+) -> Result<Vec<u8>, String> {
     // convert BGRA to RGB
-    let flat_data = flatten(bitmap.data.as_ref());
+    let mut flat_data = flatten(bitmap.data.as_ref());
+    let (mut width, mut height) = (bitmap.width, bitmap.height);
+
+    // downscale to fit in a max_size square, channel order does not matter for resizing
+    if let Some(max_size) = max_size {
+        let max_size = max_size as usize;
+        if width > max_size || height > max_size {
+            let scale = max_size as f64 / width.max(height) as f64;
+            let (new_width, new_height) = (
+                ((width as f64 * scale).round() as u32).max(1),
+                ((height as f64 * scale).round() as u32).max(1),
+            );
+            let buffer = image::RgbaImage::from_raw(width as u32, height as u32, flat_data)
+                .ok_or("Invalid bitmap size")?;
+            flat_data = image::imageops::thumbnail(&buffer, new_width, new_height).into_raw();
+            (width, height) = (new_width as usize, new_height as usize);
+        }
+    }
+
     let image = turbojpeg::Image { 
         pixels: flat_data.as_slice(),
-        width: bitmap.width,
-        height: bitmap.height, 
+        width,
+        height, 
         // size of one image row in bytes
-        pitch: bitmap.width * 4,
+        pitch: width * 4,
         format: turbojpeg::PixelFormat::BGRX 
     };
     let jpeg_data = turbojpeg::compress(image, 95, turbojpeg::Subsamp::Sub2x2).map_err(|e| e.to_string())?;
-    let base64_image = rbase64::encode(&jpeg_data);
-    Ok(base64_image)
+    Ok(jpeg_data.to_vec())
 }
 
-type WavWriterHandle = Arc<Mutex<Option<hound::WavWriter<BufWriter<File>>>>>;
-pub fn write_audio_data<T, U>(input: &[T], writer: &WavWriterHandle)
-where
-    T: Sample,
-    U: Sample + hound::Sample + FromSample<T>,
-{
-    if let Ok(mut guard) = writer.try_lock() {
-        if let Some(writer) = guard.as_mut() {
-            for &sample in input.iter() {
-                let sample: U = U::from_sample(sample);
-                writer.write_sample(sample).expect("Failed to write sample");
-            }
-        }
-    }
+pub fn make_base64_jpeg_from_bitmap<Data: BitmapDataBgra8x4>(
+    bitmap: &FrameBitmapBgraUnorm8x4<Data>,
+    max_size: Option<u32>,
+) -> Result<String, String> {
+    let jpeg_data = make_jpeg_from_bitmap(bitmap, max_size)?;
+    let base64_image = rbase64::encode(&jpeg_data);
+    Ok(base64_image)
 }
 
 pub fn is_in(rect: &Rect, x: f64, y: f64) -> bool {
@@ -127,6 +116,8 @@ pub fn match_deep_link(link: &str) -> Option<Auth> {
 pub async fn get_upload_link(
     app_handle: &AppHandle,
     file_extension: &str,
+    content_type: &str,
+    sequence: Option<usize>,
 ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
     let auth = app_handle
         .try_state::<AppState>()
@@ -138,7 +129,12 @@ pub async fn get_upload_link(
         .ok_or("No auth")?;
 
     let url = format!("https://sniive.com/api/spaces/{}/populate", auth.space_name);
-    let body = serde_json::json!({ "access": auth.access, "fileExtension": file_extension });
+    let body = serde_json::json!({
+        "access": auth.access,
+        "fileExtension": file_extension,
+        "contentType": content_type,
+        "sequence": sequence,
+    });
     let client = reqwest::Client::new();
     let res = client
         .post(&url)
@@ -151,6 +147,26 @@ pub async fn get_upload_link(
     Ok(json_result)
 }
 
+/// Streams a file from disk to an upload link, instead of reading it in memory first
+pub async fn upload_file(
+    upload_link: &str,
+    filepath: &PathBuf,
+    content_type: &str,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    let file = tokio::fs::File::open(filepath).await?;
+    let length = file.metadata().await?.len();
+
+    let client = reqwest::Client::new();
+    let res = client
+        .put(upload_link)
+        .header("Content-Type", content_type)
+        .header("Content-Length", length)
+        .header("x-ms-blob-type", "BlockBlob")
+        .body(reqwest::Body::from(file))
+        .send()
+        .await?;
+    Ok(res.status().is_success())
+}
 
 pub async fn notify_recording_status(
     app_handle: &AppHandle,
@@ -1,11 +1,54 @@
 import { AudioInputSource } from "@/components/audioInputSource";
+import { AudioLevelMeter } from "@/components/audioLevelMeter";
+import { InterruptedRecordings } from "@/components/interruptedRecordings";
 import { RecordButton } from "@/components/recordButton";
 import { VideoInputSource } from "@/components/videoInputSource";
 import { RiCloseLine, RiSubtractLine } from "@remixicon/react";
 import { getCurrentWindow } from "@tauri-apps/api/window";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { useEffect } from "react";
 import sniive from "@/assets/sniive.svg";
+import { RestoredSelection } from "@/lib/types";
+import { useAppStore } from "@/state";
 
 function AppMain() {
+  const { setSurface, setAudioDevice, setSurfaceLost } = useAppStore(
+    ({ setSurface, setAudioDevice, setSurfaceLost }) => ({
+      setSurface,
+      setAudioDevice,
+      setSurfaceLost,
+    }),
+  );
+
+  useEffect(() => {
+    const applySelection = ({ surface, audio_device }: RestoredSelection) => {
+      if (surface) setSurface(surface);
+      if (audio_device !== null) setAudioDevice(audio_device);
+    };
+
+    invoke<RestoredSelection>("get_restored_selection")
+      .then(applySelection)
+      .catch(() => false);
+    const unlisten = listen<RestoredSelection>(
+      "selection-restored",
+      ({ payload }) => applySelection(payload),
+    );
+
+    const unlistenSurfaceLost = listen("surface-lost", () =>
+      setSurfaceLost(true),
+    );
+    const unlistenSurfaceRestored = listen("surface-restored", () =>
+      setSurfaceLost(false),
+    );
+
+    return () => {
+      unlisten.then((f) => f());
+      unlistenSurfaceLost.then((f) => f());
+      unlistenSurfaceRestored.then((f) => f());
+    };
+  }, []);
+
   return (
     <main className="bg-background w-scren h-screen antialiased rounded-lg flex flex-col items-center overflow-hidden font-sans select-none">
       <header
@@ -48,7 +91,9 @@ function AppMain() {
         </div>
       </header>
       <div className="w-full h-10 relative flex items-center justify-center">
+        <AudioLevelMeter />
         <img src={sniive} alt="Sniive" className="h-5 py-0.5 z-10" />
+        <InterruptedRecordings />
       </div>
     </main>
   );
@@ -1,5 +1,5 @@
 import { useEffect, useState } from "react";
-import { Surface } from "@/lib/types";
+import { Surface, SurfaceThumbnail } from "@/lib/types";
 import { RiCloseLine, RiSubtractLine } from "@remixicon/react";
 import { getCurrentWebviewWindow } from "@tauri-apps/api/webviewWindow";
 import { ScrollArea } from "@/components/ui/scroll-area";
@@ -15,6 +15,23 @@ function AppSelectSurface() {
   const { locale } = useAppStore(({ locale }) => ({ locale }));
 
   useEffect(() => {
+    const applyThumbnail =
+      ({ id, thumbnail }: SurfaceThumbnail) =>
+      (surfaces: Surface[]) =>
+        thumbnail === null
+          ? surfaces.filter((surface) => surface.id !== id)
+          : surfaces.map((surface) =>
+              surface.id === id ? { ...surface, thumbnail } : surface,
+            );
+
+    const unlistenThumbnails = getCurrentWebviewWindow().listen<SurfaceThumbnail>(
+      "surface-thumbnail",
+      ({ payload }) => {
+        setWindows(applyThumbnail(payload));
+        setDisplays(applyThumbnail(payload));
+      },
+    );
+
     getCurrentWebviewWindow()
       .once<Surface[]>("surfaces", async ({ payload }) => {
         const windows = payload.filter(
@@ -34,6 +51,10 @@ function AppSelectSurface() {
     getCurrentWebviewWindow().onCloseRequested(async () => {
       await getCurrentWebviewWindow().emit("selected", "Aborted");
     });
+
+    return () => {
+      unlistenThumbnails.then((f) => f());
+    };
   }, []);
 
   return (
@@ -101,11 +122,15 @@ function AppSelectSurface() {
                         })
                       }
                     >
-                      <img
-                        src={`data:image/jpeg;base64,${thumbnail}`}
-                        alt={title}
-                        className="col-span-2 rounded-md max-h-20 m-auto"
-                      />
+                      {thumbnail ? (
+                        <img
+                          src={`data:image/jpeg;base64,${thumbnail}`}
+                          alt={title}
+                          className="col-span-2 rounded-md max-h-20 m-auto"
+                        />
+                      ) : (
+                        <div className="col-span-2 rounded-md h-14 w-full bg-accent animate-pulse" />
+                      )}
                       <span className="col-span-4 line-clamp-3 text-left leading-tight text-sm">
                         {title}
                       </span>
@@ -138,11 +163,15 @@ function AppSelectSurface() {
                         })
                       }
                     >
-                      <img
-                        src={`data:jpeg/png;base64,${thumbnail}`}
-                        alt={title}
-                        className="col-span-2 rounded-md m-auto max-h-20"
-                      />
+                      {thumbnail ? (
+                        <img
+                          src={`data:image/jpeg;base64,${thumbnail}`}
+                          alt={title}
+                          className="col-span-2 rounded-md m-auto max-h-20"
+                        />
+                      ) : (
+                        <div className="col-span-2 rounded-md h-14 w-full bg-accent animate-pulse" />
+                      )}
                       <span className="col-span-4 line-clamp-3 text-left leading-tight text-sm">{`Monitor n°${index + 1}`}</span>
                       <span className="col-span-2 font-semibold truncate leading-tight px-2">
                         {program}
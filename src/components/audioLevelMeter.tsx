@@ -0,0 +1,80 @@
+import { getText } from "@/lib/locales";
+import { AudioLevel, AudioWarning } from "@/lib/types";
+import { cn } from "@/lib/utils";
+import { useAppStore } from "@/state";
+import { listen } from "@tauri-apps/api/event";
+import { useEffect, useState } from "react";
+
+// levels below it show an empty meter
+const METER_FLOOR_DB = -60;
+
+export function AudioLevelMeter() {
+  const [level, setLevel] = useState<AudioLevel | null>(null);
+  const [warnings, setWarnings] = useState<AudioWarning["kind"][]>([]);
+  const { isRecording, audioDevice, locale } = useAppStore(
+    ({ isRecording, audioDevice, locale }) => ({
+      isRecording,
+      audioDevice,
+      locale,
+    }),
+  );
+
+  useEffect(() => {
+    if (!isRecording) {
+      setLevel(null);
+      setWarnings([]);
+      return;
+    }
+
+    const unlistenLevel = listen<AudioLevel>("audio-level", ({ payload }) =>
+      setLevel(payload),
+    );
+    // a warning is sent again once its condition ends
+    const unlistenWarning = listen<AudioWarning>(
+      "audio-warning",
+      ({ payload }) =>
+        setWarnings((warnings) => [
+          ...warnings.filter((kind) => kind !== payload.kind),
+          ...(payload.active ? [payload.kind] : []),
+        ]),
+    );
+
+    return () => {
+      unlistenLevel.then((f) => f());
+      unlistenWarning.then((f) => f());
+    };
+  }, [isRecording]);
+
+  if (!isRecording || !audioDevice) {
+    return null;
+  }
+
+  const fill = level
+    ? Math.min(1, Math.max(0, 1 - level.rmsDb / METER_FLOOR_DB))
+    : 0;
+  const warning = warnings[warnings.length - 1];
+
+  return (
+    <div className="absolute left-2 flex flex-row items-center gap-2">
+      <div className="h-1.5 w-16 rounded-full bg-muted overflow-hidden">
+        <div
+          className={cn(
+            "h-full transition-[width] duration-100",
+            level?.clipped ? "bg-red-500" : "bg-primary",
+          )}
+          style={{ width: `${fill * 100}%` }}
+        />
+      </div>
+      {warning && (
+        <span className="text-xs text-red-500">
+          {getText(
+            locale,
+            warning === "silence"
+              ? "audioWarningSilence"
+              : "audioWarningClipping",
+          )}
+        </span>
+      )}
+    </div>
+  );
+}
@@ -0,0 +1,66 @@
+import { Button } from "@/components/ui/button";
+import { getText } from "@/lib/locales";
+import { InterruptedRecording } from "@/lib/types";
+import { useError } from "@/lib/utils";
+import { useAppStore } from "@/state";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { useEffect, useState } from "react";
+import { useNavigate } from "react-router-dom";
+
+export function InterruptedRecordings() {
+  const navigate = useNavigate();
+  const error = useError();
+  const [recordings, setRecordings] = useState<InterruptedRecording[]>([]);
+  const [loading, setLoading] = useState<boolean>(false);
+  const { isRecording, locale } = useAppStore(({ isRecording, locale }) => ({
+    isRecording,
+    locale,
+  }));
+
+  useEffect(() => {
+    // the app lists them once it is launched, the window may open after that
+    invoke<InterruptedRecording[]>("list_interrupted_recordings")
+      .then(setRecordings)
+      .catch(() => false);
+    const unlisten = listen<InterruptedRecording[]>(
+      "interrupted-recordings",
+      ({ payload }) => setRecordings(payload),
+    );
+
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+
+  const latest = recordings.reduce<InterruptedRecording | null>(
+    (latest, recording) =>
+      !latest || recording.recordingStartTime > latest.recordingStartTime
+        ? recording
+        : latest,
+    null,
+  );
+  if (!latest || isRecording) {
+    return null;
+  }
+
+  // once recovered, it is finished or cancelled like a recording that just stopped
+  const handleRecover = async () => {
+    setLoading(true);
+    await invoke<void>("recover_recording", { id: latest.id })
+      .then(() => navigate("/result"))
+      .catch((err) => error(String(err)));
+  };
+
+  return (
+    <Button
+      variant="link"
+      size="sm"
+      className="absolute right-2 h-auto p-0 text-xs"
+      onClick={handleRecover}
+      disabled={loading}
+    >
+      {getText(locale, "interruptedRecording")}
+    </Button>
+  );
+}
@@ -16,6 +16,7 @@ export function VideoInputSource() {
   const {
     inputSourcesDisabled,
     surface,
+    surfaceLost,
     setSurface,
     setInputSourcesDisabled,
     locale,
@@ -23,12 +24,14 @@ export function VideoInputSource() {
     ({
       inputSourcesDisabled,
       surface,
+      surfaceLost,
       setSurface,
       setInputSourcesDisabled,
       locale,
     }) => ({
       inputSourcesDisabled,
       surface,
+      surfaceLost,
       setSurface,
       setInputSourcesDisabled,
       locale,
@@ -47,7 +50,8 @@ export function VideoInputSource() {
       default:
         setSurface({ ...surface.Selected });
     }
-    setInputSourcesDisabled(false);
+    // a replacement picked mid-recording must not unlock the other sources
+    setInputSourcesDisabled(useAppStore.getState().isRecording);
   };
 
   return (
@@ -57,7 +61,7 @@ export function VideoInputSource() {
           <Button
             variant="ghost"
             onClick={handleClick}
-            disabled={inputSourcesDisabled}
+            disabled={inputSourcesDisabled && !surfaceLost}
             className="relative"
           >
             <ScreenShareIcon className="w-6 h-6" />
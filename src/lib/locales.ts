@@ -1,12 +1,15 @@
 type TextsKeys =
   | "audioInputUnknown"
   | "audioInputNoSource"
+  | "audioWarningSilence"
+  | "audioWarningClipping"
   | "videoInputUnknown"
   | "videoInputNoSource"
   | "results"
   | "resultsSubmit"
   | "resultsCancel"
   | "resultsDone"
+  | "interruptedRecording"
   | "error"
   | "selectionQuestion"
   | "selectionNone"
@@ -20,12 +23,15 @@ type TextsKeys =
 const TEXTS_EN: Record<TextsKeys, string> = {
   audioInputUnknown: "Unknown source",
   audioInputNoSource: "No audio source selected",
+  audioWarningSilence: "No sound from the microphone",
+  audioWarningClipping: "The microphone is too loud",
   videoInputUnknown: "Unknown source",
   videoInputNoSource: "No video source selected",
   results: "Is everything okay?",
   resultsSubmit: "Yes",
   resultsCancel: "Cancel",
   resultsDone: "Done!",
+  interruptedRecording: "Finish interrupted recording",
   error: "Error",
   selectionQuestion: "What do you want to record?",
   selectionNone: "None",
@@ -40,12 +46,15 @@ const TEXTS_EN: Record<TextsKeys, string> = {
 const TEXTS_FR: Record<TextsKeys, string> = {
   audioInputUnknown: "Inconnu",
   audioInputNoSource: "Aucune source audio sélectionnée",
+  audioWarningSilence: "Aucun son du micro",
+  audioWarningClipping: "Le micro sature",
   videoInputUnknown: "Support limité - Source inconnue",
   videoInputNoSource: "Aucune source vidéo sélectionnée",
   results: "Tout est bon ?",
   resultsSubmit: "Oui",
   resultsCancel: "Annuler",
   resultsDone: "Terminé !",
+  interruptedRecording: "Terminer l'enregistrement interrompu",
   error: "Erreur",
   selectionQuestion: "Que voulez-vous enregistrer ?",
   selectionNone: "Aucun",
@@ -6,7 +6,11 @@ export interface Surface {
   id: number;
   title: string;
   program: string;
-  thumbnail: string;
+  thumbnail?: string;
+}
+export interface SurfaceThumbnail {
+  id: number;
+  thumbnail: string | null;
 }
 export type SurfaceOutput = Output<{ title: string; thumbnail: string }>;
 
@@ -15,3 +19,119 @@ export interface AudioDevice {
   id: number;
 }
 export type AudioDeviceOutput = Output<string>;
+
+export interface RestoredSelection {
+  surface: { title: string; thumbnail: string } | null;
+  audio_device: string | null;
+}
+
+export interface AudioPreview {
+  original: string;
+  processed: string;
+}
+
+export interface AudioSettings {
+  loudness: {
+    target_lufs: number;
+    true_peak_ceiling_dbtp: number;
+    max_gain_db: number;
+  };
+  silence: {
+    vad_threshold_db: number;
+    trim_silence: boolean;
+    shorten_pauses: boolean;
+    max_pause_ms: number;
+    padding_ms: number;
+  };
+  dsp: {
+    high_pass: { enabled: boolean; cutoff_hz: number };
+    noise_suppression: { enabled: boolean; reduction_db: number };
+    noise_gate: {
+      enabled: boolean;
+      threshold_db: number;
+      attenuation_db: number;
+      release_ms: number;
+    };
+  };
+  keyboard_ducking: {
+    enabled: boolean;
+    attenuation_db: number;
+    before_ms: number;
+    after_ms: number;
+  };
+  system_audio: {
+    enabled: boolean;
+    device: string | null;
+    mode: "mix" | "separate_track";
+    microphone_level_db: number;
+    system_level_db: number;
+  };
+  encoding: {
+    format: "wav" | "flac" | "opus";
+    opus_bitrate_kbps: number;
+  };
+  level_monitor: {
+    silence_threshold_db: number;
+    silence_warning_ms: number;
+    clipping_warning_ms: number;
+  };
+  step_cues: {
+    padding_ms: number;
+  };
+  input_configs: Record<string, InputConfig>;
+}
+
+export interface InputConfig {
+  sample_rate: number;
+  channels: number;
+}
+
+export interface AudioConfigs {
+  configs: InputConfig[];
+  default: InputConfig;
+  selected: InputConfig | null;
+}
+
+export interface AudioDeviceTest {
+  statistics: {
+    sampleRate: number;
+    channels: number;
+    measuredSampleRate: number;
+    droppedFrames: number;
+    rmsDb: number;
+    peakDb: number;
+    noiseFloorDb: number;
+    clippedSamples: number;
+  };
+  preview: string | null;
+}
+
+export interface AudioLevel {
+  rmsDb: number;
+  peakDb: number;
+  clipped: boolean;
+}
+
+export interface AudioWarning {
+  kind: "silence" | "clipping";
+  active: boolean;
+  durationMs: number;
+}
+
+export interface AudioDeviceLostEvent {
+  device: string;
+  reason: "Disconnected" | "Stalled";
+}
+
+export interface AudioDeviceRestoredEvent {
+  device: string;
+  fallback: boolean;
+}
+
+export interface InterruptedRecording {
+  id: string;
+  spaceName: string;
+  recordingStartTime: number;
+  steps: number;
+  pendingSteps: number;
+}
@@ -5,12 +5,16 @@ type AppStore = {
   inputSourcesDisabled: boolean;
   recordButtonDisabled: boolean;
   isRecording: boolean;
+  surfaceLost: boolean;
   audioDevice: Selected<AudioDeviceOutput> | null;
+  // a device or "none" was chosen, a recording without audio has only its steps
+  audioDeviceChosen: boolean;
   surface: Selected<SurfaceOutput> | null;
   locale: string;
   setInputSourcesDisabled: (disabled: boolean) => void;
   setRecordButtonDisabled: (disabled: boolean) => void;
   setIsRecording: (isRecording: boolean) => void;
+  setSurfaceLost: (surfaceLost: boolean) => void;
   setAudioDevice: (audioDevice: Selected<AudioDeviceOutput> | null) => void;
   setSurface: (surface: Selected<SurfaceOutput> | null) => void;
   setLocale: (locale: string) => void;
@@ -20,7 +24,9 @@ export const useAppStore = create<AppStore>((set, get) => ({
   inputSourcesDisabled: false,
   recordButtonDisabled: true,
   isRecording: false,
+  surfaceLost: false,
   audioDevice: null,
+  audioDeviceChosen: false,
   surface: null,
   locale: "en",
   setInputSourcesDisabled: (disabled) =>
@@ -35,18 +41,19 @@ export const useAppStore = create<AppStore>((set, get) => ({
         recordButtonDisabled: false,
       });
     } else {
-      set({ isRecording });
+      set({ isRecording, surfaceLost: false });
     }
   },
+  setSurfaceLost: (surfaceLost) => set({ surfaceLost }),
   setAudioDevice: (audioDevice) => {
-    if (get().surface && audioDevice) {
-      set({ audioDevice, recordButtonDisabled: false });
+    if (get().surface) {
+      set({ audioDevice, audioDeviceChosen: true, recordButtonDisabled: false });
     } else {
-      set({ audioDevice, recordButtonDisabled: true });
+      set({ audioDevice, audioDeviceChosen: true, recordButtonDisabled: true });
     }
   },
   setSurface: (surface) => {
-    if (get().audioDevice && surface) {
+    if (get().audioDeviceChosen && surface) {
       set({ surface, recordButtonDisabled: false });
     } else {
       set({ surface, recordButtonDisabled: true });